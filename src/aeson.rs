@@ -0,0 +1,358 @@
+// JSON encoding of the original haskell server, as produced by the generic Aeson instances with
+// the default options :
+// - enumerations are strings : "Heart", "DeclarePointElder"
+// - records are objects : {"rank":"Seven","suit":"Heart"}
+// - other sum types are tagged objects : {"tag":"PlayCard","contents":{...}}, with a list as
+//   contents for constructors with several fields and no contents for constructors without any
+// - hands are insertion ordered sets (OSet), encoded as the list of their cards in the order
+//   they were added, without the duplicates
+//
+// The haskell server has no Sink move : it can't be encoded nor decoded.
+//
+// Can be used with `#[serde(with = "piquet::aeson")]` or through to_string / from_str.
+//
+// The golden files of tests/golden/aeson are written from these rules, not captured from the
+// haskell server : the field names, the order of the fields and the order of the hands still
+// have to be checked against a capture (see docs/todo.md).
+use std::fmt::Debug;
+
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
+use serde::ser::{self, SerializeStruct, SerializeSeq, SerializeTuple};
+use serde_json::Value;
+
+use crate::cards::*;
+use crate::combinations::*;
+use crate::game::*;
+
+pub trait AesonEncoding: Sized {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+    fn from_aeson(value: &Value) -> Result<Self, String>;
+}
+
+pub fn serialize<T: AesonEncoding, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    value.serialize_aeson(serializer)
+}
+
+pub fn deserialize<'de, T: AesonEncoding, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    T::from_aeson(&value).map_err(D::Error::custom)
+}
+
+pub fn to_string<T: AesonEncoding>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&Aeson(value))
+}
+
+pub fn from_str<T: AesonEncoding>(json: &str) -> Result<T, serde_json::Error> {
+    let value: Value = serde_json::from_str(json)?;
+    T::from_aeson(&value).map_err(serde_json::Error::custom)
+}
+
+// Serializes the wrapped value with the Aeson encoding
+struct Aeson<'a, T>(&'a T);
+
+impl<'a, T: AesonEncoding> Serialize for Aeson<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_aeson(serializer)
+    }
+}
+
+// -----------  Enumerations -----------
+fn serialize_name<T: Debug, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{:?}", value))
+}
+
+fn from_name<T: Debug>(value: &Value, what: &str, candidates: impl Iterator<Item = T>) -> Result<T, String> {
+    let name = value.as_str().ok_or_else(|| format!("expected a string for {}, got {}", what, value))?;
+    candidates.into_iter()
+        .find(|candidate| format!("{:?}", candidate) == name)
+        .ok_or_else(|| format!("unknown {} {}", what, name))
+}
+
+impl AesonEncoding for Suit {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_name(self, serializer)
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        from_name(value, "suit", Suit::iter().cloned())
+    }
+}
+
+impl AesonEncoding for Rank {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_name(self, serializer)
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        from_name(value, "rank", Rank::iter().cloned())
+    }
+}
+
+impl AesonEncoding for CombinationType {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_name(self, serializer)
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        let ctypes = [CombinationType::Point, CombinationType::Sequence, CombinationType::Set];
+        from_name(value, "combination type", ctypes.iter().cloned())
+    }
+}
+
+impl AesonEncoding for DeclarationResponse {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_name(self, serializer)
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        let responses = [DeclarationResponse::Good, DeclarationResponse::NotGood, DeclarationResponse::Equals];
+        from_name(value, "declaration response", responses.iter().cloned())
+    }
+}
+
+impl AesonEncoding for Step {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_name(self, serializer)
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        from_name(value, "step", std::iter::successors(Some(Step::Start), |step| step.succ()))
+    }
+}
+
+// -----------  Records -----------
+fn field<'a>(value: &'a Value, name: &str) -> Result<&'a Value, String> {
+    value.get(name).ok_or_else(|| format!("missing field {} in {}", name, value))
+}
+
+impl AesonEncoding for Card {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut card = serializer.serialize_struct("Card", 2)?;
+        card.serialize_field("rank", &Aeson(&self.rank))?;
+        card.serialize_field("suit", &Aeson(&self.suit))?;
+        card.end()
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        Ok(Card::new(Rank::from_aeson(field(value, "rank")?)?, Suit::from_aeson(field(value, "suit")?)?))
+    }
+}
+
+// Cards in order, keeping the first of the duplicates as an OSet does
+fn unique_cards<I: Iterator<Item = Card>>(cards: I) -> Vec<Card> {
+    let mut unique: Vec<Card> = vec![];
+    for card in cards {
+        if !unique.contains(&card) {
+            unique.push(card);
+        }
+    }
+    unique
+}
+
+impl AesonEncoding for Hand {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let cards = unique_cards(self.iter().cloned());
+        let mut seq = serializer.serialize_seq(Some(cards.len()))?;
+        for card in cards.iter() {
+            seq.serialize_element(&Aeson(card))?;
+        }
+        seq.end()
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        let items = value.as_array().ok_or_else(|| format!("expected a list of cards, got {}", value))?;
+        let cards = items.iter().map(Card::from_aeson).collect::<Result<Vec<_>, _>>()?;
+        Ok(Hand::new(unique_cards(cards.into_iter())))
+    }
+}
+
+impl AesonEncoding for Combination {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut comb = serializer.serialize_struct("Combination", 2)?;
+        comb.serialize_field("combinationType", &Aeson(self.get_type()))?;
+        comb.serialize_field("cards", &Aeson(self.get_cards()))?;
+        comb.end()
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        let ctype = CombinationType::from_aeson(field(value, "combinationType")?)?;
+        Ok(Combination::new(ctype, Hand::from_aeson(field(value, "cards")?)?))
+    }
+}
+
+// -----------  Tagged sum -----------
+// contents of a constructor with two fields
+struct Pair<'a, A, B>(&'a A, &'a B);
+
+impl<'a, A: AesonEncoding, B: AesonEncoding> Serialize for Pair<'a, A, B> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut pair = serializer.serialize_tuple(2)?;
+        pair.serialize_element(&Aeson(self.0))?;
+        pair.serialize_element(&Aeson(self.1))?;
+        pair.end()
+    }
+}
+
+impl AesonEncoding for u32 {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(*self)
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        value.as_u64()
+            .filter(|&n| n <= u32::MAX as u64)
+            .map(|n| n as u32)
+            .ok_or_else(|| format!("expected a number, got {}", value))
+    }
+}
+
+fn pair<A: AesonEncoding, B: AesonEncoding>(value: &Value) -> Result<(A, B), String> {
+    match value.as_array().map(|items| items.as_slice()) {
+        Some([first, second]) => Ok((A::from_aeson(first)?, B::from_aeson(second)?)),
+        _ => Err(format!("expected a list of two values, got {}", value)),
+    }
+}
+
+fn serialize_tagged<S: Serializer, C: Serialize>(serializer: S, tag: &str, contents: Option<C>) -> Result<S::Ok, S::Error> {
+    let mut tagged = serializer.serialize_struct("Tagged", if contents.is_some() { 2 } else { 1 })?;
+    tagged.serialize_field("tag", tag)?;
+    if let Some(contents) = contents {
+        tagged.serialize_field("contents", &contents)?;
+    }
+    tagged.end()
+}
+
+impl AesonEncoding for PlayerMove {
+    fn serialize_aeson<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use PlayerMove::*;
+        let tag = format!("{:?}", self);
+        let tag = tag.split('(').next().expect("Move name");
+        match self {
+            Exchange(hand) => serialize_tagged(serializer, tag, Some(Aeson(hand))),
+            DeclarationCount(ctype, count) => serialize_tagged(serializer, tag, Some(Pair(ctype, count))),
+            DeclarationUpper(ctype, rank) => serialize_tagged(serializer, tag, Some(Pair(ctype, rank))),
+            PlayerResponse(ctype, response) => serialize_tagged(serializer, tag, Some(Pair(ctype, response))),
+            Declaration(comb) => serialize_tagged(serializer, tag, Some(Aeson(comb))),
+            PlayFirst(card) | PlayCard(card) => serialize_tagged(serializer, tag, Some(Aeson(card))),
+            Sink(_) => Err(ser::Error::custom("no Sink move in the haskell encoding")),
+            _ => serialize_tagged::<S, Aeson<Card>>(serializer, tag, None),
+        }
+    }
+
+    fn from_aeson(value: &Value) -> Result<Self, String> {
+        use PlayerMove::*;
+        let tag = field(value, "tag")?.as_str().ok_or_else(|| format!("expected a string tag in {}", value))?;
+        let contents = || field(value, "contents");
+        Ok(match tag {
+            "CarteBlanche" => CarteBlanche,
+            "CarteRouge" => CarteRouge,
+            "Exchange" => Exchange(Hand::from_aeson(contents()?)?),
+            "DeclarationCount" => { let (ctype, count) = pair(contents()?)?; DeclarationCount(ctype, count) },
+            "DeclarationUpper" => { let (ctype, rank) = pair(contents()?)?; DeclarationUpper(ctype, rank) },
+            "PlayerResponse" => { let (ctype, response) = pair(contents()?)?; PlayerResponse(ctype, response) },
+            "Declaration" => Declaration(Combination::from_aeson(contents()?)?),
+            "Repique" => Repique,
+            "PlayFirst" => PlayFirst(Card::from_aeson(contents()?)?),
+            "Pique" => Pique,
+            "WinAsSecond" => WinAsSecond,
+            "WinLastTrick" => WinLastTrick,
+            "PlayCard" => PlayCard(Card::from_aeson(contents()?)?),
+            "WinCards" => WinCards,
+            "Capot" => Capot,
+            _ => return Err(format!("unknown move {}", tag)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // one value per line, as expected from the haskell server
+    fn golden(contents: &str) -> Vec<&str> {
+        contents.lines().filter(|line| !line.is_empty()).collect()
+    }
+
+    fn check_golden<T: AesonEncoding + PartialEq + Debug>(contents: &str, values: Vec<T>) {
+        let lines = golden(contents);
+        assert_eq!(lines.len(), values.len());
+        for (line, value) in lines.into_iter().zip(values) {
+            assert_eq!(to_string(&value).unwrap(), line);
+            assert_eq!(from_str::<T>(line).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_golden_cards() {
+        check_golden(include_str!("../tests/golden/aeson/card.json"), vec![
+            Card::new(Rank::Seven, Suit::Heart),
+            Card::new(Rank::Ten, Suit::Club),
+            Card::new(Rank::Ace, Suit::Spade),
+        ]);
+        check_golden(include_str!("../tests/golden/aeson/hand.json"), vec![
+            Hand::empty_hand(),
+            "7h Ks 7s 10d".parse::<Hand>().unwrap(),
+        ]);
+        check_golden(include_str!("../tests/golden/aeson/combination.json"), vec![
+            Combination::new(CombinationType::Set, "Kh Kd Ks".parse().unwrap()),
+            Combination::new(CombinationType::Sequence, "9c 10c Jc".parse().unwrap()),
+        ]);
+    }
+
+    #[test]
+    fn test_golden_moves() {
+        use PlayerMove::*;
+        check_golden(include_str!("../tests/golden/aeson/player_move.json"), vec![
+            CarteBlanche,
+            CarteRouge,
+            Exchange("7d 8h".parse().unwrap()),
+            DeclarationCount(CombinationType::Point, 5),
+            DeclarationUpper(CombinationType::Sequence, Rank::Queen),
+            PlayerResponse(CombinationType::Set, DeclarationResponse::NotGood),
+            Declaration(Combination::new(CombinationType::Point, "Ah".parse().unwrap())),
+            Repique,
+            PlayFirst("As".parse().unwrap()),
+            Pique,
+            WinAsSecond,
+            WinLastTrick,
+            PlayCard("10d".parse().unwrap()),
+            WinCards,
+            Capot,
+        ]);
+        check_golden(include_str!("../tests/golden/aeson/step.json"),
+                     std::iter::successors(Some(Step::Start), |step| step.succ()).collect());
+    }
+
+    #[test]
+    fn test_hand_order() {
+        // the order of the cards is kept, not the duplicates
+        let hand: Hand = "Ks 7h Ks".parse().unwrap();
+        assert_eq!(to_string(&hand).unwrap(), to_string(&"Ks 7h".parse::<Hand>().unwrap()).unwrap());
+        assert_eq!(from_str::<Hand>(r#"[{"suit":"Spade","rank":"King"},{"rank":"Seven","suit":"Heart"},{"rank":"King","suit":"Spade"}]"#).unwrap(),
+                   "Ks 7h".parse().unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(from_str::<Card>(r#"{"rank":"Seven"}"#).is_err());
+        assert!(from_str::<Suit>(r#""Hearts""#).is_err());
+        assert!(from_str::<PlayerMove>(r#"{"tag":"Exchange"}"#).is_err());
+        assert!(from_str::<PlayerMove>(r#"{"tag":"DeclarationCount","contents":["Point"]}"#).is_err());
+        assert!(from_str::<PlayerMove>(r#"{"tag":"Sink","contents":"Point"}"#).is_err());
+        assert!(to_string(&PlayerMove::Sink(CombinationType::Set)).is_err());
+    }
+
+    #[test]
+    fn test_serde_with() {
+        #[derive (Serialize, Deserialize, PartialEq, Debug)]
+        struct Message {
+            #[serde(with = "crate::aeson")]
+            card: Card,
+        }
+        let message = Message { card: Card::new(Rank::Jack, Suit::Diamond) };
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(json, r#"{"card":{"rank":"Jack","suit":"Diamond"}}"#);
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+    }
+}
@@ -0,0 +1,207 @@
+// Players of a game, human or bots : an agent chooses its move from what it is allowed to see
+use std::time::Duration;
+use rand::Rng;
+use rand_core::SeedableRng;
+
+use crate::cards::*;
+use crate::game::*;
+use crate::view::PlayerView;
+
+pub trait Agent {
+    // `legal_moves` is never empty
+    fn choose_move(&mut self, view: &PlayerView, legal_moves: &[PlayerMove]) -> PlayerMove;
+
+    // time allowed for the next moves, ignored by the agents which don't search
+    fn set_time_limit(&mut self, _time: Option<Duration>) {}
+
+    // moves the agent failed to choose, a legal move being played in its place
+    fn failures(&self) -> usize { 0 }
+}
+
+// Asks the player to play its move, returns the moves recorded
+pub fn play_agent_move(game: &mut Game, player: PlayerId, agent: &mut dyn Agent) -> Result<Vec<Move>, PiquetError> {
+    let legal_moves = game.legal_moves(player);
+    if legal_moves.is_empty() {
+        return Err(PiquetError::InvalidForStepError(game.get_step().clone()));
+    }
+    let pmove = agent.choose_move(&game.view_for(player), &legal_moves);
+    game.play(player, pmove)
+}
+
+// Plays the game until the end of the partie, dealing when needed
+pub fn play_partie(game: &mut Game, player1: &mut dyn Agent, player2: &mut dyn Agent) -> Result<(), PiquetError> {
+    if game.get_step() == &Step::Start {
+        game.choose_elder();
+    }
+    while !game.is_partie_over() {
+        match game.player_to_play() {
+            Some(PlayerId::P1) => { play_agent_move(game, PlayerId::P1, player1)?; },
+            Some(PlayerId::P2) => { play_agent_move(game, PlayerId::P2, player2)?; },
+            None => game.deal()?,
+        }
+    }
+    Ok(())
+}
+
+// -----------  Random -----------
+pub struct RandomAgent { rng: rand_xorshift::XorShiftRng }
+
+impl RandomAgent {
+    pub fn new(seed: [u8; 16]) -> Self {
+        RandomAgent { rng: rand_xorshift::XorShiftRng::from_seed(seed) }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn choose_move(&mut self, _view: &PlayerView, legal_moves: &[PlayerMove]) -> PlayerMove {
+        legal_moves[self.rng.gen_range(0, legal_moves.len())].clone()
+    }
+}
+
+// -----------  Greedy -----------
+// Points the move scores right away, including winning the trick as second
+fn immediate_points(view: &PlayerView, pmove: &PlayerMove) -> usize {
+    match (pmove, &view.opponentCardPlayed) {
+        (PlayerMove::PlayCard(card), Some(led)) if card.suit == led.suit && card.rank > led.rank => 1,
+        _ => pmove.movePoints()
+    }
+}
+
+// Declares the most it can when points are even
+fn declaration_size(pmove: &PlayerMove) -> u32 {
+    match pmove {
+        PlayerMove::DeclarationCount(_, count) => *count + 1,
+        PlayerMove::DeclarationUpper(_, rank) => rank.point_value() + 1,
+        PlayerMove::PlayerResponse(_, _) => 1,
+        _ => 0
+    }
+}
+
+pub struct GreedyAgent;
+
+impl Agent for GreedyAgent {
+    fn choose_move(&mut self, view: &PlayerView, legal_moves: &[PlayerMove]) -> PlayerMove {
+        legal_moves.iter()
+            .rev()
+            .max_by_key(|pmove| (immediate_points(view, pmove), declaration_size(pmove)))
+            .expect("No legal move")
+            .clone()
+    }
+}
+
+// -----------  Rules of thumb -----------
+// How much a card is worth keeping : high cards, long suits and sets
+fn keep_value(hand: &Hand, card: &Card) -> u32 {
+    let suit_length = hand.iter().filter(|c| c.suit == card.suit).count();
+    let same_rank = hand.iter().filter(|c| c.rank == card.rank).count();
+    let mut value = card.rank.clone() as u32;
+    if suit_length >= 5 {
+        value += 8;
+    }
+    if card.rank >= Rank::Ten && same_rank >= 3 {
+        value += 8;
+    }
+    value
+}
+
+pub struct RulesAgent;
+
+impl RulesAgent {
+    // discards as many low cards as allowed
+    fn choose_exchange(&self, view: &PlayerView, legal_moves: &[PlayerMove]) -> PlayerMove {
+        let max_cards = legal_moves.iter().filter_map(|pmove| match pmove {
+            PlayerMove::Exchange(discard) => Some(discard.len()),
+            _ => None
+        }).max().unwrap_or(0);
+        let mut cards: Vec<&Card> = view.hand.iter().collect();
+        cards.sort_by_key(|card| keep_value(&view.hand, card));
+        let discard = CardSet::from(&Hand::new(cards.into_iter().take(max_cards).cloned().collect()));
+        legal_moves.iter()
+            .find(|pmove| matches!(pmove, PlayerMove::Exchange(hand) if CardSet::from(hand) == discard))
+            .unwrap_or(&legal_moves[0])
+            .clone()
+    }
+
+    // wins the trick as cheaply as possible, or gives the lowest card
+    fn choose_card(&self, view: &PlayerView, legal_moves: &[PlayerMove]) -> PlayerMove {
+        let card_of = |pmove: &PlayerMove| match pmove {
+            PlayerMove::PlayFirst(card) | PlayerMove::PlayCard(card) => Some(card.clone()),
+            _ => None
+        };
+        let cards: Vec<(Card, &PlayerMove)> = legal_moves.iter()
+            .filter_map(|pmove| card_of(pmove).map(|card| (card, pmove)))
+            .collect();
+        let chosen = match &view.opponentCardPlayed {
+            Some(led) => cards.iter()
+                .filter(|(card, _)| card.suit == led.suit && card.rank > led.rank)
+                .min_by_key(|(card, _)| card.rank.clone())
+                .or_else(|| cards.iter().min_by_key(|(card, _)| card.rank.clone())),
+            // leads its best card, from its longest suit when even
+            None => cards.iter().max_by_key(|(card, _)| {
+                (card.rank.clone(), view.hand.iter().filter(|c| c.suit == card.suit).count())
+            }),
+        };
+        chosen.map_or(&legal_moves[0], |(_, pmove)| pmove).clone()
+    }
+}
+
+impl Agent for RulesAgent {
+    fn choose_move(&mut self, view: &PlayerView, legal_moves: &[PlayerMove]) -> PlayerMove {
+        use Step::*;
+        match view.step {
+            ExchangeElder | ExchangeYounger => {
+                // carte blanche is always worth declaring
+                if legal_moves.contains(&PlayerMove::CarteBlanche) {
+                    return PlayerMove::CarteBlanche;
+                }
+                self.choose_exchange(view, legal_moves)
+            },
+            PlayFirstCard | PlayCards => self.choose_card(view, legal_moves),
+            _ => GreedyAgent.choose_move(view, legal_moves)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combinations::*;
+    use crate::game::fixtures::game_at_declaration;
+
+    fn partie(seed: u8, player1: &mut dyn Agent, player2: &mut dyn Agent) -> PartieResult {
+        let mut game = Game::new([seed; 16]);
+        play_partie(&mut game, player1, player2).unwrap();
+        game.get_partie_result().unwrap()
+    }
+
+    #[test]
+    fn test_play_partie() {
+        let result = partie(1, &mut RandomAgent::new([1; 16]), &mut GreedyAgent);
+        assert!(result.player1Points > 0 || result.player2Points > 0);
+        partie(2, &mut RulesAgent, &mut GreedyAgent);
+        partie(3, &mut RulesAgent, &mut RulesAgent);
+    }
+
+    #[test]
+    fn test_greedy_choice() {
+        let game = game_at_declaration(4);
+        let elder = game.elder();
+        // declares its best point
+        let view = game.view_for(elder);
+        let pmove = GreedyAgent.choose_move(&view, &game.legal_moves(elder));
+        let best = get_best_combination(&CombinationType::Point, game.get_hand(elder)).unwrap();
+        assert_eq!(pmove, PlayerMove::DeclarationCount(CombinationType::Point, best.len() as u32));
+    }
+
+    #[test]
+    fn test_rules_beat_random() {
+        let mut wins = 0;
+        for seed in 0..10 {
+            let result = partie(seed, &mut RulesAgent, &mut RandomAgent::new([seed; 16]));
+            if result.winner == Some(PlayerId::P1) {
+                wins += 1;
+            }
+        }
+        assert!(wins >= 7);
+    }
+}
@@ -0,0 +1,433 @@
+// REST service for the web client :
+//
+//   POST /games[?seed=<32 hexadecimal digits>]        new game, dealt   -> { id, seed }
+//   POST /games/{id}/players/{1|2}                    join the game     -> { token }
+//   GET  /games/{id}/players/{1|2}/state              redacted state    -> PlayerView
+//   POST /games/{id}/players/{1|2}/moves  PlayerMove  play              -> [Move]
+//
+// The state and the moves need the header `Authorization: Bearer <token>` given when joining.
+// Errors are answered with the matching status code and a body { error, message }.
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use actix_web::{http, App, HttpRequest, HttpResponse, Json, Path, Query, ResponseError, State};
+use actix_web::dev::{JsonConfig, PathConfig};
+use serde::{Serialize, Deserialize};
+
+use crate::game::*;
+use crate::notation::{parse_seed, seed_text};
+use crate::view::PlayerView;
+
+#[derive (Debug, Clone, PartialEq, Eq)]
+pub enum ApiError { NoSuchGame
+                  , NoSuchPlayer
+                  , InvalidSeed(String)
+                  , Unauthorized
+                  , Game(PiquetError)
+                  // a handler panicked while holding the games
+                  , Internal
+}
+
+impl ApiError {
+    pub fn status(&self) -> http::StatusCode {
+        use http::StatusCode;
+        match self {
+            ApiError::NoSuchGame | ApiError::NoSuchPlayer => StatusCode::NOT_FOUND,
+            ApiError::InvalidSeed(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Game(err) => match err {
+                PiquetError::NotYourTurnError
+                    | PiquetError::InvalidForStepError(_)
+                    | PiquetError::AlreadyConnectedError
+                    | PiquetError::NotConnectedError
+                    | PiquetError::NoSuchMove => StatusCode::CONFLICT,
+                PiquetError::InvalidCombination
+                    | PiquetError::InvalidExchange
+                    | PiquetError::CardNotInHand
+                    | PiquetError::MustFollowSuit => StatusCode::UNPROCESSABLE_ENTITY,
+                PiquetError::UnknownCommand => StatusCode::BAD_REQUEST,
+                PiquetError::UndoDisabled => StatusCode::FORBIDDEN,
+            }
+        }
+    }
+
+    // for the clients to match on, part of the API : not to be changed when renaming a variant
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::NoSuchGame => "NoSuchGame",
+            ApiError::NoSuchPlayer => "NoSuchPlayer",
+            ApiError::InvalidSeed(_) => "InvalidSeed",
+            ApiError::Unauthorized => "Unauthorized",
+            ApiError::Internal => "Internal",
+            ApiError::Game(err) => match err {
+                PiquetError::NotYourTurnError => "NotYourTurnError",
+                PiquetError::InvalidForStepError(_) => "InvalidForStepError",
+                PiquetError::InvalidCombination => "InvalidCombination",
+                PiquetError::InvalidExchange => "InvalidExchange",
+                PiquetError::CardNotInHand => "CardNotInHand",
+                PiquetError::MustFollowSuit => "MustFollowSuit",
+                PiquetError::AlreadyConnectedError => "AlreadyConnectedError",
+                PiquetError::NotConnectedError => "NotConnectedError",
+                PiquetError::UnknownCommand => "UnknownCommand",
+                PiquetError::UndoDisabled => "UndoDisabled",
+                PiquetError::NoSuchMove => "NoSuchMove",
+            }
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::NoSuchGame => write!(f, "no such game"),
+            ApiError::NoSuchPlayer => write!(f, "no such player, expected 1 or 2"),
+            ApiError::InvalidSeed(seed) => write!(f, "invalid seed '{}', expected 32 hexadecimal digits", seed),
+            ApiError::Unauthorized => write!(f, "missing or invalid player token"),
+            ApiError::Internal => write!(f, "internal server error"),
+            ApiError::Game(err) => match err {
+                PiquetError::NotYourTurnError => write!(f, "not your turn"),
+                PiquetError::InvalidForStepError(step) => write!(f, "move not allowed at step {:?}", step),
+                PiquetError::InvalidCombination => write!(f, "invalid combination"),
+                PiquetError::InvalidExchange => write!(f, "invalid exchange"),
+                PiquetError::CardNotInHand => write!(f, "card not in hand"),
+                PiquetError::MustFollowSuit => write!(f, "must follow suit"),
+                PiquetError::AlreadyConnectedError => write!(f, "player already joined"),
+                PiquetError::NotConnectedError => write!(f, "waiting for the other player to join"),
+                PiquetError::UnknownCommand => write!(f, "unknown command"),
+                PiquetError::UndoDisabled => write!(f, "undo is disabled in competitive games"),
+                PiquetError::NoSuchMove => write!(f, "no move to undo or redo"),
+            }
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[derive (Serialize)]
+struct ErrorBody { error: &'static str
+                 , message: String
+}
+
+impl ResponseError for ApiError {
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status()).json(ErrorBody { error: self.code(), message: self.to_string() })
+    }
+}
+
+impl From<PiquetError> for ApiError {
+    fn from(err: PiquetError) -> Self {
+        ApiError::Game(err)
+    }
+}
+
+// -----------  Games -----------
+// sessions not used for this time are removed, finished or abandoned
+const SESSION_TIMEOUT: Duration = Duration::from_secs(3600);
+const MAX_SESSIONS: usize = 10000;
+
+struct Session { game: Game
+               // token of each player, once joined
+               , tokens: [Option<String>; 2]
+               , last_used: Instant
+}
+
+pub struct Games { sessions: HashMap<usize, Session>
+                 , next_id: usize
+                 , capacity: usize
+}
+
+impl Default for Games {
+    fn default() -> Self {
+        Games::with_capacity(MAX_SESSIONS)
+    }
+}
+
+fn player_id(number: u8) -> Result<PlayerId, ApiError> {
+    match number {
+        1 => Ok(PlayerId::P1),
+        2 => Ok(PlayerId::P2),
+        _ => Err(ApiError::NoSuchPlayer)
+    }
+}
+
+impl Games {
+    pub fn new() -> Self {
+        Games::default()
+    }
+
+    // Keeps no more than `capacity` sessions, the least recently used being removed first
+    pub fn with_capacity(capacity: usize) -> Self {
+        Games { sessions: HashMap::new(), next_id: 0, capacity: capacity.max(1) }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    // Removes the sessions not used since SESSION_TIMEOUT before `now`
+    pub fn remove_expired(&mut self, now: Instant) {
+        self.sessions.retain(|_, session| now.saturating_duration_since(session.last_used) < SESSION_TIMEOUT);
+    }
+
+    // New game, ready for elder to exchange
+    pub fn create(&mut self, seed: [u8; 16]) -> usize {
+        let now = Instant::now();
+        self.remove_expired(now);
+        while self.sessions.len() >= self.capacity {
+            let oldest = self.sessions.iter().min_by_key(|(_, session)| session.last_used).map(|(&id, _)| id);
+            self.sessions.remove(&oldest.expect("No session"));
+        }
+        let mut game = Game::new(seed);
+        game.choose_elder();
+        game.deal().expect("Could not deal");
+        self.next_id += 1;
+        self.sessions.insert(self.next_id, Session { game, tokens: [None, None], last_used: now });
+        self.next_id
+    }
+
+    fn session(&mut self, id: usize) -> Result<&mut Session, ApiError> {
+        let session = self.sessions.get_mut(&id).ok_or(ApiError::NoSuchGame)?;
+        session.last_used = Instant::now();
+        Ok(session)
+    }
+
+    // Session of the player, checking the player's token
+    fn player_session(&mut self, id: usize, player: PlayerId, token: Option<&str>) -> Result<&mut Session, ApiError> {
+        let session = self.session(id)?;
+        match (&session.tokens[player as usize], token) {
+            (Some(expected), Some(token)) if expected == token => Ok(session),
+            _ => Err(ApiError::Unauthorized)
+        }
+    }
+
+    // Gives the token which identifies the player in the following requests
+    pub fn join(&mut self, id: usize, player: PlayerId) -> Result<String, ApiError> {
+        let session = self.session(id)?;
+        let seat = &mut session.tokens[player as usize];
+        if seat.is_some() {
+            return Err(PiquetError::AlreadyConnectedError.into());
+        }
+        let token = format!("{:032x}", rand::random::<u128>());
+        *seat = Some(token.clone());
+        Ok(token)
+    }
+
+    pub fn state(&mut self, id: usize, player: PlayerId, token: Option<&str>) -> Result<PlayerView, ApiError> {
+        Ok(self.player_session(id, player, token)?.game.view_for(player))
+    }
+
+    // Plays the move once both players have joined, then deals the next deal if needed
+    pub fn play(&mut self, id: usize, player: PlayerId, token: Option<&str>, pmove: PlayerMove) -> Result<Vec<Move>, ApiError> {
+        let session = self.player_session(id, player, token)?;
+        if session.tokens.iter().any(|token| token.is_none()) {
+            return Err(PiquetError::NotConnectedError.into());
+        }
+        let game = &mut session.game;
+        let moves = game.play(player, pmove)?;
+        if game.get_step() == &Step::End && !game.is_partie_over() {
+            game.deal()?;
+        }
+        Ok(moves)
+    }
+}
+
+// -----------  Handlers -----------
+pub struct AppState { games: Arc<Mutex<Games>> }
+
+#[derive (Deserialize)]
+struct NewGame { seed: Option<String> }
+
+#[derive (Serialize)]
+struct CreatedGame { id: usize
+                   , seed: String
+}
+
+#[derive (Serialize)]
+struct Joined { token: String }
+
+fn bearer_token(req: &HttpRequest<AppState>) -> Option<String> {
+    req.headers().get(http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
+// The games may be inconsistent once a handler has panicked while holding them
+fn lock_games(state: &AppState) -> Result<MutexGuard<'_, Games>, ApiError> {
+    state.games.lock().map_err(|_| ApiError::Internal)
+}
+
+fn create_game((query, state): (Query<NewGame>, State<AppState>)) -> Result<HttpResponse, ApiError> {
+    let seed = match &query.seed {
+        Some(seed) => parse_seed(seed).map_err(|_| ApiError::InvalidSeed(seed.clone()))?,
+        None => rand::random()
+    };
+    let id = lock_games(&state)?.create(seed);
+    Ok(HttpResponse::Created().json(CreatedGame { id, seed: seed_text(&seed) }))
+}
+
+fn join_game((path, state): (Path<(usize, u8)>, State<AppState>)) -> Result<Json<Joined>, ApiError> {
+    let player = player_id(path.1)?;
+    let token = lock_games(&state)?.join(path.0, player)?;
+    Ok(Json(Joined { token }))
+}
+
+fn get_state((req, path): (HttpRequest<AppState>, Path<(usize, u8)>)) -> Result<Json<PlayerView>, ApiError> {
+    let player = player_id(path.1)?;
+    let token = bearer_token(&req);
+    let view = lock_games(req.state())?.state(path.0, player, token.as_deref())?;
+    Ok(Json(view))
+}
+
+fn post_move((req, path, pmove): (HttpRequest<AppState>, Path<(usize, u8)>, Json<PlayerMove>)) -> Result<Json<Vec<Move>>, ApiError> {
+    let player = player_id(path.1)?;
+    let token = bearer_token(&req);
+    let moves = lock_games(req.state())?.play(path.0, player, token.as_deref(), pmove.into_inner())?;
+    Ok(Json(moves))
+}
+
+// Malformed paths and moves are answered as the other errors, instead of actix's plain text
+fn path_config(config: &mut PathConfig<AppState>) {
+    config.error_handler(|_, _| ApiError::Game(PiquetError::UnknownCommand).into());
+}
+
+fn json_config(config: &mut JsonConfig<AppState>) {
+    config.error_handler(|_, _| ApiError::Game(PiquetError::UnknownCommand).into());
+}
+
+// The application of a server worker, all the workers sharing the games
+pub fn create_app(games: Arc<Mutex<Games>>) -> App<AppState> {
+    App::with_state(AppState { games })
+        .resource("/games", |r| r.method(http::Method::POST).with(create_game))
+        .resource("/games/{id}/players/{player}", |r| r.method(http::Method::POST)
+                  .with_config(join_game, |((path, _),)| path_config(path)))
+        .resource("/games/{id}/players/{player}/state", |r| r.method(http::Method::GET)
+                  .with_config(get_state, |((_, path),)| path_config(path)))
+        .resource("/games/{id}/players/{player}/moves", |r| r.method(http::Method::POST)
+                  .with_config(post_move, |((_, path, json),)| { path_config(path); json_config(json); }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test;
+
+    #[test]
+    fn test_games() {
+        let mut games = Games::new();
+        let id = games.create([1; 16]);
+        assert_eq!(games.join(id + 1, PlayerId::P1), Err(ApiError::NoSuchGame));
+        let token1 = games.join(id, PlayerId::P1).unwrap();
+        assert_eq!(games.join(id, PlayerId::P1), Err(ApiError::Game(PiquetError::AlreadyConnectedError)));
+        assert_eq!(games.state(id, PlayerId::P1, None), Err(ApiError::Unauthorized));
+        assert_eq!(games.state(id, PlayerId::P2, Some(&token1)), Err(ApiError::Unauthorized));
+        let view = games.state(id, PlayerId::P1, Some(&token1)).unwrap();
+        assert_eq!(view.hand.len(), 12);
+
+        let elder = if view.isElder { PlayerId::P1 } else { PlayerId::P2 };
+        let sink = PlayerMove::Sink(crate::combinations::CombinationType::Point);
+        assert_eq!(games.play(id, PlayerId::P1, Some(&token1), sink.clone()), Err(ApiError::Game(PiquetError::NotConnectedError)));
+        let token2 = games.join(id, PlayerId::P2).unwrap();
+        let tokens = [token1, token2];
+        let token = |player: PlayerId| Some(tokens[player as usize].as_str());
+        assert_eq!(games.play(id, elder.other(), token(elder.other()), sink.clone()), Err(ApiError::Game(PiquetError::NotYourTurnError)));
+        assert_eq!(games.play(id, elder, token(elder), sink).unwrap_err().status(), http::StatusCode::CONFLICT);
+        let card = view.hand.iter().next().unwrap().clone();
+        let discard = PlayerMove::Exchange(crate::cards::Hand::new(vec![card]));
+        assert_eq!(games.play(id, elder, token(elder), discard.clone()), Ok(vec![Move::new(elder, discard)]));
+
+        // idle sessions expire
+        games.remove_expired(Instant::now() + SESSION_TIMEOUT / 2);
+        assert_eq!(games.len(), 1);
+        games.remove_expired(Instant::now() + SESSION_TIMEOUT);
+        assert!(games.is_empty());
+        assert_eq!(games.join(id, PlayerId::P1), Err(ApiError::NoSuchGame));
+        // the least recently used session is removed first
+        let mut games = Games::with_capacity(2);
+        let (first, second) = (games.create([1; 16]), games.create([2; 16]));
+        std::thread::sleep(Duration::from_millis(1));
+        games.join(first, PlayerId::P1).unwrap();
+        let third = games.create([3; 16]);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games.join(second, PlayerId::P1), Err(ApiError::NoSuchGame));
+        assert!(games.join(third, PlayerId::P1).is_ok());
+    }
+
+    #[test]
+    fn test_error_response() {
+        let response = ApiError::Game(PiquetError::MustFollowSuit).error_response();
+        assert_eq!(response.status(), http::StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(ApiError::Game(PiquetError::InvalidForStepError(Step::End)).code(), "InvalidForStepError");
+        assert_eq!(ApiError::NoSuchGame.status(), http::StatusCode::NOT_FOUND);
+    }
+
+    // minimal HTTP client : status and JSON body of the answer
+    fn request(server: &test::TestServer, method: &str, path: &str, token: Option<&str>, body: Option<String>) -> (u16, serde_json::Value) {
+        use std::io::{Read, Write};
+        let mut stream = std::net::TcpStream::connect(server.addr()).unwrap();
+        let body = body.unwrap_or_default();
+        let mut head = format!("{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n", method, path, body.len());
+        if let Some(token) = token {
+            head.push_str(&format!("Authorization: Bearer {}\r\n", token));
+        }
+        write!(stream, "{}\r\n{}", head, body).unwrap();
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).unwrap();
+        let status = answer[9..12].parse().unwrap();
+        let json = answer.split("\r\n\r\n").nth(1).and_then(|body| serde_json::from_str(body).ok()).unwrap_or_default();
+        (status, json)
+    }
+
+    #[test]
+    fn test_endpoints() {
+        let games = Arc::new(Mutex::new(Games::new()));
+        let server = test::TestServer::with_factory(move || create_app(games.clone()));
+
+        let (status, created) = request(&server, "POST", "/games?seed=01010101010101010101010101010101", None, None);
+        assert_eq!(status, 201);
+        assert_eq!(created["seed"], "01010101010101010101010101010101");
+        let id = created["id"].as_u64().unwrap();
+        let (status, error) = request(&server, "POST", "/games?seed=zz", None, None);
+        assert_eq!((status, error["error"].as_str()), (400, Some("InvalidSeed")));
+        assert_eq!(request(&server, "POST", "/games/1/players/3", None, None).0, 404);
+
+        let (_, joined) = request(&server, "POST", &format!("/games/{}/players/1", id), None, None);
+        let token = joined["token"].as_str().unwrap();
+        let state = format!("/games/{}/players/1/state", id);
+        assert_eq!(request(&server, "GET", &state, None, None).0, 401);
+        let (status, view) = request(&server, "GET", &state, Some(token), None);
+        assert_eq!(status, 200);
+        let view: PlayerView = serde_json::from_value(view).unwrap();
+        assert_eq!(view.player, PlayerId::P1);
+
+        let pmove = serde_json::to_string(&PlayerMove::CarteBlanche).unwrap();
+        let (status, error) = request(&server, "POST", &format!("/games/{}/players/1/moves", id), Some(token), Some(pmove));
+        assert_eq!((status, error["error"].as_str()), (409, Some("NotConnectedError")));
+
+        // malformed requests
+        let (status, error) = request(&server, "POST", &format!("/games/{}/players/1/moves", id), Some(token), Some(r#"{"Bogus":1}"#.to_string()));
+        assert_eq!((status, error["error"].as_str()), (400, Some("UnknownCommand")));
+        let (status, error) = request(&server, "GET", "/games/first/players/1/state", Some(token), None);
+        assert_eq!((status, error["error"].as_str()), (400, Some("UnknownCommand")));
+    }
+
+    #[test]
+    fn test_poisoned_games() {
+        let games = Arc::new(Mutex::new(Games::new()));
+        let poisoned = games.clone();
+        std::thread::spawn(move || {
+            let _guard = poisoned.lock().unwrap();
+            panic!("handler failure");
+        }).join().unwrap_err();
+        let server = test::TestServer::with_factory(move || create_app(games.clone()));
+        let (status, error) = request(&server, "POST", "/games", None, None);
+        assert_eq!((status, error["error"].as_str()), (500, Some("Internal")));
+    }
+}
@@ -0,0 +1,304 @@
+// Self-play arena : parties between two agents, to measure whether a change of a bot helps
+use std::fmt;
+use rand::Rng;
+use rand_core::SeedableRng;
+use serde::{Serialize, Deserialize};
+
+use crate::agent::*;
+use crate::game::*;
+use crate::ismcts::{IsmctsAgent, IsmctsConfig};
+use crate::protocol::EngineAgent;
+
+// 95% confidence
+const Z: f64 = 1.96;
+// mean Elo rating of the two agents
+const ELO_BASE: f64 = 1500.0;
+
+// Agents known by name : random, greedy, rules, ismcts[:iterations], ismcts-oracle[:iterations],
+// or engine:<command> for an engine speaking the line protocol
+pub fn agent_by_name(name: &str, seed: [u8; 16]) -> Result<Box<dyn Agent>, String> {
+    if let Some(command) = name.strip_prefix("engine:") {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("missing engine command")?;
+        let args: Vec<&str> = words.collect();
+        let engine = EngineAgent::spawn(program, &args, None).map_err(|err| format!("{}: {}", command, err))?;
+        return Ok(Box::new(engine));
+    }
+    let mut parts = name.splitn(2, ':');
+    let kind = parts.next().unwrap_or("");
+    let iterations = match parts.next() {
+        Some(count) => Some(count.parse::<usize>().map_err(|_| format!("invalid iterations count '{}'", count))?),
+        None => None
+    };
+    let ismcts = |oracle| {
+        let default = IsmctsConfig::default();
+        let config = IsmctsConfig { iterations: iterations.unwrap_or(default.iterations), oracle, ..default };
+        Box::new(IsmctsAgent::new(config, seed))
+    };
+    match (kind, iterations) {
+        ("random", None) => Ok(Box::new(RandomAgent::new(seed))),
+        ("greedy", None) => Ok(Box::new(GreedyAgent)),
+        ("rules", None) => Ok(Box::new(RulesAgent)),
+        ("ismcts", _) => Ok(ismcts(false)),
+        ("ismcts-oracle", _) => Ok(ismcts(true)),
+        _ => Err(format!("unknown agent '{}', expected one of random greedy rules ismcts[:iterations] ismcts-oracle[:iterations] engine:<command>", name))
+    }
+}
+
+#[derive (Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Interval { pub low: f64
+                    , pub high: f64
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{:.3}, {:.3}]", self.low, self.high)
+    }
+}
+
+// Wilson score interval of a proportion
+fn proportion_interval(successes: f64, count: usize) -> Interval {
+    if count == 0 {
+        return Interval { low: 0.0, high: 1.0 };
+    }
+    let n = count as f64;
+    let p = successes / n;
+    let denominator = 1.0 + Z * Z / n;
+    let center = (p + Z * Z / (2.0 * n)) / denominator;
+    let margin = Z * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt() / denominator;
+    Interval { low: (center - margin).max(0.0), high: (center + margin).min(1.0) }
+}
+
+// Normal approximation of the interval of a mean
+fn mean_interval(values: &[f64]) -> (f64, Interval) {
+    let n = values.len() as f64;
+    if values.is_empty() {
+        return (0.0, Interval { low: 0.0, high: 0.0 });
+    }
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+    let margin = Z * (variance / n).sqrt();
+    (mean, Interval { low: mean - margin, high: mean + margin })
+}
+
+// Elo rating difference for which the expected score of the stronger agent is `score`. The 
+// score is kept within half a party of 0 and 1, so that winning all of them stays finite.
+fn elo_difference(score: f64, parties: usize) -> f64 {
+    let margin = 0.5 / parties.max(1) as f64;
+    let score = score.max(margin).min(1.0 - margin);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+#[derive (Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentStats { pub name: String
+                      , pub wins: usize
+                      , pub draws: usize
+                      , pub losses: usize
+                      // a draw counts as half a win
+                      , pub win_rate: f64
+                      , pub win_rate_interval: Interval
+                      , pub mean_deal_points: f64
+                      , pub deal_points_interval: Interval
+                      // moves the agent failed to choose, played for it
+                      , pub failures: usize
+                      // from the win rate against the other agent, the ratings averaging ELO_BASE
+                      , pub elo: f64
+                      , pub elo_interval: Interval
+                      // by deal
+                      , pub pique: f64
+                      , pub repique: f64
+                      , pub capot: f64
+}
+
+#[derive (Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArenaReport { pub parties: usize
+                       , pub deals: usize
+                       , pub first: AgentStats
+                       , pub second: AgentStats
+                       // first agent's deal points minus the second one's
+                       , pub mean_deal_difference: f64
+                       , pub deal_difference_interval: Interval
+}
+
+impl fmt::Display for ArenaReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} parties, {} deals", self.parties, self.deals)?;
+        for stats in [&self.first, &self.second].iter() {
+            writeln!(f, "{}: {} wins, {} draws, {} losses", stats.name, stats.wins, stats.draws, stats.losses)?;
+            writeln!(f, "  win rate {:.3} {}", stats.win_rate, stats.win_rate_interval)?;
+            writeln!(f, "  points by deal {:.3} {}", stats.mean_deal_points, stats.deal_points_interval)?;
+            if stats.failures > 0 {
+                writeln!(f, "  failed to choose {} moves", stats.failures)?;
+            }
+            writeln!(f, "  elo {:.0} [{:.0}, {:.0}]", stats.elo, stats.elo_interval.low, stats.elo_interval.high)?;
+            writeln!(f, "  pique {:.3}, repique {:.3}, capot {:.3}", stats.pique, stats.repique, stats.capot)?;
+        }
+        write!(f, "difference by deal {:.3} {}", self.mean_deal_difference, self.deal_difference_interval)
+    }
+}
+
+// Results of one agent over the parties
+#[derive (Default)]
+struct Tally { wins: usize
+             , draws: usize
+             , losses: usize
+             , deal_points: Vec<f64>
+             , piques: usize
+             , repiques: usize
+             , capots: usize
+             , failures: usize
+}
+
+impl Tally {
+    fn stats(&self, name: &str) -> AgentStats {
+        let parties = self.wins + self.draws + self.losses;
+        let won = self.wins as f64 + self.draws as f64 / 2.0;
+        let deals = self.deal_points.len().max(1) as f64;
+        let (mean_deal_points, deal_points_interval) = mean_interval(&self.deal_points);
+        let win_rate = won / parties.max(1) as f64;
+        let win_rate_interval = proportion_interval(won, parties);
+        let elo = |score| ELO_BASE + elo_difference(score, parties) / 2.0;
+        AgentStats { name: name.to_string()
+                   , wins: self.wins
+                   , draws: self.draws
+                   , losses: self.losses
+                   , win_rate
+                   , win_rate_interval
+                   , mean_deal_points
+                   , deal_points_interval
+                   , failures: self.failures
+                   , elo: elo(win_rate)
+                   , elo_interval: Interval { low: elo(win_rate_interval.low), high: elo(win_rate_interval.high) }
+                   , pique: self.piques as f64 / deals
+                   , repique: self.repiques as f64 / deals
+                   , capot: self.capots as f64 / deals
+        }
+    }
+}
+
+// Seeds of the parties : each one is played twice, the agents exchanging their seats, so that
+// they get the same cards and are elder in the same deals. An odd number of parties is rounded
+// up, so that no agent gets a seat more often than the other.
+pub fn party_seeds(master_seed: u64, parties: usize) -> Vec<[u8; 16]> {
+    let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(master_seed);
+    let seeds: Vec<[u8; 16]> = (0..parties.div_ceil(2)).map(|_| rng.gen()).collect();
+    seeds.iter().flat_map(|&seed| vec![seed, seed]).collect()
+}
+
+// Plays the parties, the first agent being player 1 of the even ones and player 2 of the odd ones
+pub fn run_arena(parties: usize, master_seed: u64, first: (&str, &mut dyn Agent), second: (&str, &mut dyn Agent)) -> Result<ArenaReport, PiquetError> {
+    let (first_name, first_agent) = first;
+    let (second_name, second_agent) = second;
+    let mut tallies = [Tally::default(), Tally::default()];
+    let mut differences = vec![];
+    let failures = [first_agent.failures(), second_agent.failures()];
+    let seeds = party_seeds(master_seed, parties);
+    for (idx, &seed) in seeds.iter().enumerate() {
+        let mut game = Game::new(seed);
+        let swapped = idx % 2 == 1;
+        if swapped {
+            play_partie(&mut game, second_agent, first_agent)?;
+        } else {
+            play_partie(&mut game, first_agent, second_agent)?;
+        }
+        let seats = if swapped { [PlayerId::P2, PlayerId::P1] } else { [PlayerId::P1, PlayerId::P2] };
+
+        let winner = game.get_partie_result().expect("Partie not over").winner;
+        for (tally, &player) in tallies.iter_mut().zip(seats.iter()) {
+            match winner {
+                Some(p) if p == player => tally.wins += 1,
+                Some(_) => tally.losses += 1,
+                None => tally.draws += 1,
+            }
+        }
+        for (_, moves) in game.get_deals().iter() {
+            let mut points = [0.0, 0.0];
+            for (tally, (&player, points)) in tallies.iter_mut().zip(seats.iter().zip(points.iter_mut())) {
+                for (gmove, move_points) in moves.iter().filter(|(gmove, _)| gmove.player() == player) {
+                    *points += *move_points as f64;
+                    match gmove.player_move() {
+                        PlayerMove::Pique => tally.piques += 1,
+                        PlayerMove::Repique => tally.repiques += 1,
+                        PlayerMove::Capot => tally.capots += 1,
+                        _ => ()
+                    }
+                }
+                tally.deal_points.push(*points);
+            }
+            differences.push(points[0] - points[1]);
+        }
+    }
+
+    tallies[0].failures = first_agent.failures() - failures[0];
+    tallies[1].failures = second_agent.failures() - failures[1];
+
+    let (mean_deal_difference, deal_difference_interval) = mean_interval(&differences);
+    Ok(ArenaReport { parties: seeds.len()
+                   , deals: differences.len()
+                   , first: tallies[0].stats(first_name)
+                   , second: tallies[1].stats(second_name)
+                   , mean_deal_difference
+                   , deal_difference_interval
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_by_name() {
+        assert!(agent_by_name("rules", [0; 16]).is_ok());
+        assert!(agent_by_name("ismcts:10", [0; 16]).is_ok());
+        assert!(agent_by_name("ismcts:ten", [0; 16]).is_err());
+        assert!(agent_by_name("rules:10", [0; 16]).is_err());
+        assert!(agent_by_name("perfect", [0; 16]).is_err());
+    }
+
+    #[test]
+    fn test_intervals() {
+        let interval = proportion_interval(50.0, 100);
+        assert!(interval.low < 0.5 && interval.high > 0.5);
+        assert!((interval.low + interval.high - 1.0).abs() < 1e-9);
+        assert_eq!(proportion_interval(0.0, 10).low, 0.0);
+        let (mean, interval) = mean_interval(&[1.0, 2.0, 3.0]);
+        assert_eq!(mean, 2.0);
+        assert!(interval.low < 2.0 && interval.high > 2.0);
+
+        assert_eq!(elo_difference(0.5, 10), 0.0);
+        assert!((elo_difference(0.76, 100) - 200.0).abs() < 1.0);
+        assert!((elo_difference(1.0, 10) + elo_difference(0.0, 10)).abs() < 1e-9);
+        assert!(elo_difference(1.0, 10).is_finite());
+    }
+
+    #[test]
+    fn test_run_arena() {
+        let seeds = party_seeds(1, 4);
+        assert_eq!(seeds[0], seeds[1]);
+        assert_ne!(seeds[1], seeds[2]);
+        assert_eq!(party_seeds(1, 3), seeds);
+
+        let run = || {
+            let mut random = RandomAgent::new([1; 16]);
+            run_arena(4, 1, ("rules", &mut RulesAgent), ("random", &mut random)).unwrap()
+        };
+        let report = run();
+        assert_eq!(report, run());
+        assert_eq!(report.first.wins, report.second.losses);
+        assert_eq!(report.first.wins + report.first.draws + report.first.losses, 4);
+        assert_eq!(report.deals, 4 * 6);
+        assert!(report.first.win_rate > report.second.win_rate);
+        assert_eq!((report.first.failures, report.second.failures), (0, 0));
+        assert!(report.first.elo > report.second.elo);
+        assert!((report.first.elo + report.second.elo - 2.0 * ELO_BASE).abs() < 1e-9);
+        for stats in [&report.first, &report.second].iter() {
+            assert!(stats.elo_interval.low <= stats.elo && stats.elo <= stats.elo_interval.high);
+        }
+        let difference = report.first.mean_deal_points - report.second.mean_deal_points;
+        assert!((difference - report.mean_deal_difference).abs() < 1e-9);
+
+        // both agents sit as often in each seat
+        let mut random = RandomAgent::new([1; 16]);
+        assert_eq!(run_arena(3, 1, ("rules", &mut RulesAgent), ("random", &mut random)).unwrap(), report);
+    }
+}
@@ -0,0 +1,72 @@
+// Plays parties between two agents and reports how they fare
+//   arena [--parties N] [--seed SEED] [--movetime MS] AGENT1 AGENT2
+use std::process;
+use std::time::Duration;
+use rand::Rng;
+use rand_core::SeedableRng;
+
+use piquet::arena::{agent_by_name, run_arena};
+
+const USAGE: &str = "usage: arena [--parties N] [--seed SEED] [--movetime MS] AGENT1 AGENT2
+each partie is played twice, the agents exchanging their seats : an odd number of parties is rounded up
+agents: random greedy rules ismcts[:iterations] ismcts-oracle[:iterations] engine:<command>";
+
+struct Args { parties: usize
+            , seed: u64
+            // time allowed to the agents for each move
+            , movetime: Option<Duration>
+            , agents: Vec<String>
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args { parties: 100, seed: rand::random(), movetime: None, agents: vec![] };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--parties" | "--seed" | "--movetime" => {
+                let value = iter.next().ok_or(format!("missing value for {}", arg))?;
+                let number = value.parse::<u64>().map_err(|_| format!("invalid value '{}' for {}", value, arg))?;
+                match arg.as_str() {
+                    "--parties" => args.parties = number as usize,
+                    "--seed" => args.seed = number,
+                    _ => args.movetime = Some(Duration::from_millis(number)),
+                }
+            },
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => args.agents.push(arg),
+        }
+    }
+    if args.agents.len() != 2 {
+        return Err(USAGE.to_string());
+    }
+    Ok(args)
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        process::exit(1);
+    });
+    // the agents' own randomness also derives from the master seed
+    let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(args.seed);
+    let mut agents = vec![];
+    for name in args.agents.iter() {
+        let mut agent = agent_by_name(name, rng.gen()).unwrap_or_else(|message| {
+            eprintln!("{}", message);
+            process::exit(1);
+        });
+        agent.set_time_limit(args.movetime);
+        agents.push(agent);
+    }
+    let mut second = agents.pop().expect("Missing agent");
+    let mut first = agents.pop().expect("Missing agent");
+
+    println!("master seed {}", args.seed);
+    match run_arena(args.parties, args.seed, (&args.agents[0], first.as_mut()), (&args.agents[1], second.as_mut())) {
+        Ok(report) => println!("{}", report),
+        Err(err) => {
+            eprintln!("invalid move: {:?}", err);
+            process::exit(1);
+        }
+    }
+}
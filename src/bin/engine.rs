@@ -0,0 +1,28 @@
+// Runs one of our bots as an engine speaking the line protocol on stdin / stdout
+//   engine AGENT
+use std::io;
+use std::process;
+
+use piquet::arena::agent_by_name;
+use piquet::protocol::serve;
+
+const USAGE: &str = "usage: engine AGENT
+agents: random greedy rules ismcts[:iterations] ismcts-oracle[:iterations]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() != 1 || args[0] == "-h" || args[0] == "--help" {
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+    let name = &args[0];
+    let mut agent = agent_by_name(name, rand::random()).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        process::exit(1);
+    });
+    let stdin = io::stdin();
+    if let Err(err) = serve(name, agent.as_mut(), stdin.lock(), io::stdout()) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
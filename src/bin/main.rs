@@ -1,28 +1,13 @@
 extern crate actix_web;
-use actix_web::{server, http, App, HttpRequest};
-use std::cell::Cell;
+use actix_web::server;
+use std::sync::{Arc, Mutex};
 
-// This struct represents state
-struct AppState {
-    counter: Cell<usize>,
-}
-
-fn index(req: &HttpRequest<AppState>) -> String {
-    let count = req.state().counter.get() + 1; // <- get count
-    req.state().counter.set(count); // <- store new count in state
-
-    format!("Request number: {}", count) // <- response with count
-}
-
-use piquet::run;
+use piquet::api::{create_app, Games};
 
 fn main() {
-    run();
-    server::new(|| {
-        App::with_state(AppState { counter: Cell::new(0) })
-            .resource("/", |r| r.method(http::Method::GET).f(index))
-            .finish()
-    }).bind("127.0.0.1:8088")
-    .unwrap()
+    let games = Arc::new(Mutex::new(Games::new()));
+    server::new(move || create_app(games.clone()))
+        .bind("127.0.0.1:8088")
+        .unwrap()
         .run();
 }
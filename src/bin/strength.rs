@@ -0,0 +1,50 @@
+// Tells how good a hand is for the declarations, by dealing the other cards at random to the
+// opponent
+//   strength [--younger] [--samples N] CARDS
+use std::process;
+
+use piquet::cards::{CardSet, Hand};
+use piquet::game::HAND_SIZE;
+use piquet::strength::evaluate_hand;
+
+const USAGE: &str = "usage: strength [--younger] [--samples N] CARDS
+cards: the twelve cards of the hand, as in \"7h 8h 9h 10h Jh Ah As Ad Kc 7s 8d 7c\"";
+
+struct Args { is_elder: bool
+            , samples: usize
+            , hand: Hand
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut is_elder = true;
+    let mut samples = 1000;
+    let mut cards = vec![];
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--younger" => is_elder = false,
+            "--samples" => {
+                let value = iter.next().ok_or("missing value for --samples")?;
+                samples = value.parse().map_err(|_| format!("invalid value '{}' for --samples", value))?;
+            },
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => cards.push(arg),
+        }
+    }
+    let hand: Hand = cards.join(" ").parse().map_err(|err| format!("{}\n{}", err, USAGE))?;
+    if hand.len() != HAND_SIZE || CardSet::from(&hand).len() != HAND_SIZE {
+        return Err(format!("expected {} different cards, got {}\n{}", HAND_SIZE, hand.len(), USAGE));
+    }
+    Ok(Args { is_elder, samples, hand })
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        process::exit(1);
+    });
+    let strength = evaluate_hand(&args.hand, args.is_elder, args.samples, &mut rand::thread_rng());
+    println!("declaration points {:.1}", strength.declaration_points);
+    println!("wins point {:.2}, sequence {:.2}, set {:.2}", strength.point_win, strength.sequence_win, strength.set_win);
+    println!("pique {:.2}, repique {:.2}", strength.pique, strength.repique);
+}
@@ -0,0 +1,454 @@
+// Compact binary encoding, the counterpart of the haskell Binary instances.
+// Values are written as a stream of bits, most significant first : 5 bits per card, a 32 bits
+// mask for card sets. Encoded data starts with a version byte.
+use std::fmt;
+
+use crate::cards::*;
+use crate::combinations::*;
+use crate::game::*;
+use crate::record::GameRecord;
+
+pub const VERSION: u8 = 1;
+
+#[derive (Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError { UnsupportedVersion(u8)
+                     , UnexpectedEnd
+                     , InvalidValue(&'static str)
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported format version {} (expected {})", v, VERSION),
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of data"),
+            DecodeError::InvalidValue(what) => write!(f, "invalid {}", what),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+pub fn encode<T: Binary>(value: &T) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    writer.put_bits(VERSION as u32, 8);
+    value.put(&mut writer);
+    writer.bytes
+}
+
+pub fn decode<T: Binary>(bytes: &[u8]) -> Result<T, DecodeError> {
+    let mut reader = BitReader::new(bytes);
+    let version = reader.get_bits(8)? as u8;
+    if version != VERSION {
+        return Err(DecodeError::UnsupportedVersion(version));
+    }
+    T::get(&mut reader)
+}
+
+// -----------  Bit streams -----------
+#[derive (Debug, Default)]
+pub struct BitWriter { bytes: Vec<u8>
+                     , used: u32  // bits used in the last byte
+}
+
+impl BitWriter {
+    pub fn put_bits(&mut self, value: u32, count: u32) {
+        for i in (0..count).rev() {
+            if self.bytes.is_empty() || self.used == 8 {
+                self.bytes.push(0);
+                self.used = 0;
+            }
+            let bit = ((value >> i) & 1) as u8;
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= bit << (7 - self.used);
+            self.used += 1;
+        }
+    }
+
+    pub fn put_bool(&mut self, value: bool) {
+        self.put_bits(value as u32, 1);
+    }
+
+    // 7 bits groups, small values take one byte
+    pub fn put_varint(&mut self, mut value: u32) {
+        loop {
+            let group = value & 0x7f;
+            value >>= 7;
+            self.put_bool(value != 0);
+            self.put_bits(group, 7);
+            if value == 0 {
+                break;
+            }
+        }
+    }
+}
+
+pub struct BitReader<'a> { bytes: &'a [u8]
+                         , position: usize // in bits
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        BitReader { bytes, position: 0 }
+    }
+
+    pub fn get_bits(&mut self, count: u32) -> Result<u32, DecodeError> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = self.bytes.get(self.position / 8).ok_or(DecodeError::UnexpectedEnd)?;
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    pub fn get_bool(&mut self) -> Result<bool, DecodeError> {
+        Ok(self.get_bits(1)? == 1)
+    }
+
+    pub fn get_varint(&mut self) -> Result<u32, DecodeError> {
+        let mut value: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let more = self.get_bool()?;
+            value |= (self.get_bits(7)? as u64) << shift;
+            shift += 7;
+            if value > u32::MAX as u64 {
+                return Err(DecodeError::InvalidValue("integer"));
+            }
+            if !more {
+                return Ok(value as u32);
+            }
+        }
+    }
+}
+
+// -----------  Encoded types -----------
+pub trait Binary: Sized {
+    fn put(&self, writer: &mut BitWriter);
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError>;
+}
+
+impl Binary for Rank {
+    fn put(&self, writer: &mut BitWriter) {
+        writer.put_bits(self.clone() as u32, 3);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        let index = reader.get_bits(3)?;
+        Ok(Rank::iter().nth(index as usize).expect("3 bits rank").clone())
+    }
+}
+
+impl Binary for Card {
+    fn put(&self, writer: &mut BitWriter) {
+        writer.put_bits(self.index(), 5);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        Ok(Card::from_index(reader.get_bits(5)?))
+    }
+}
+
+impl Binary for CardSet {
+    fn put(&self, writer: &mut BitWriter) {
+        writer.put_bits(self.bits(), 32);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        Ok(CardSet::from_bits(reader.get_bits(32)?))
+    }
+}
+
+// ordered cards : count on 6 bits then 5 bits per card
+fn put_cards<'a>(cards: impl ExactSizeIterator<Item = &'a Card>, writer: &mut BitWriter) {
+    writer.put_bits(cards.len() as u32, 6);
+    for card in cards {
+        card.put(writer);
+    }
+}
+
+// the same card can't be twice in a hand or a deck
+fn get_cards(reader: &mut BitReader) -> Result<Vec<Card>, DecodeError> {
+    let count = reader.get_bits(6)?;
+    if count > 32 {
+        return Err(DecodeError::InvalidValue("card count"));
+    }
+    let mut seen = CardSet::empty();
+    (0..count).map(|_| {
+        let card = Card::get(reader)?;
+        if seen.contains(&card) {
+            return Err(DecodeError::InvalidValue("duplicate card"));
+        }
+        seen.insert(&card);
+        Ok(card)
+    }).collect()
+}
+
+impl Binary for Hand {
+    fn put(&self, writer: &mut BitWriter) {
+        put_cards(self.iter(), writer);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        get_cards(reader).map(Hand::new)
+    }
+}
+
+impl Binary for Deck {
+    fn put(&self, writer: &mut BitWriter) {
+        put_cards(self.get_cards().iter(), writer);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        let mut deck = Deck::empty_deck();
+        deck.add_cards(get_cards(reader)?);
+        Ok(deck)
+    }
+}
+
+impl Binary for CombinationType {
+    fn put(&self, writer: &mut BitWriter) {
+        let index = match self {
+            CombinationType::Point => 0,
+            CombinationType::Sequence => 1,
+            CombinationType::Set => 2,
+        };
+        writer.put_bits(index, 2);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        match reader.get_bits(2)? {
+            0 => Ok(CombinationType::Point),
+            1 => Ok(CombinationType::Sequence),
+            2 => Ok(CombinationType::Set),
+            _ => Err(DecodeError::InvalidValue("combination type")),
+        }
+    }
+}
+
+impl Binary for DeclarationResponse {
+    fn put(&self, writer: &mut BitWriter) {
+        let index = match self {
+            DeclarationResponse::Good => 0,
+            DeclarationResponse::NotGood => 1,
+            DeclarationResponse::Equals => 2,
+        };
+        writer.put_bits(index, 2);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        match reader.get_bits(2)? {
+            0 => Ok(DeclarationResponse::Good),
+            1 => Ok(DeclarationResponse::NotGood),
+            2 => Ok(DeclarationResponse::Equals),
+            _ => Err(DecodeError::InvalidValue("declaration response")),
+        }
+    }
+}
+
+impl Binary for Combination {
+    fn put(&self, writer: &mut BitWriter) {
+        self.get_type().put(writer);
+        self.get_cards().put(writer);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        let ctype = CombinationType::get(reader)?;
+        Ok(Combination::new(ctype, Hand::get(reader)?))
+    }
+}
+
+// the 16 moves would fill 4 bits : the fifth one leaves room for new moves
+const MOVE_TAG_BITS: u32 = 5;
+
+// tag in the order of the enum, followed by the content
+impl Binary for PlayerMove {
+    fn put(&self, writer: &mut BitWriter) {
+        use PlayerMove::*;
+        let tag = match self {
+            CarteBlanche => 0,
+            CarteRouge => 1,
+            Exchange(_) => 2,
+            DeclarationCount(_, _) => 3,
+            DeclarationUpper(_, _) => 4,
+            PlayerResponse(_, _) => 5,
+            Declaration(_) => 6,
+            Repique => 7,
+            PlayFirst(_) => 8,
+            Pique => 9,
+            WinAsSecond => 10,
+            WinLastTrick => 11,
+            PlayCard(_) => 12,
+            WinCards => 13,
+            Capot => 14,
+            Sink(_) => 15,
+        };
+        writer.put_bits(tag, MOVE_TAG_BITS);
+        match self {
+            Exchange(hand) => hand.put(writer),
+            DeclarationCount(ctype, count) => { ctype.put(writer); writer.put_varint(*count) },
+            DeclarationUpper(ctype, rank) => { ctype.put(writer); rank.put(writer) },
+            PlayerResponse(ctype, response) => { ctype.put(writer); response.put(writer) },
+            Declaration(comb) => comb.put(writer),
+            PlayFirst(card) | PlayCard(card) => card.put(writer),
+            Sink(ctype) => ctype.put(writer),
+            _ => ()
+        }
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        use PlayerMove::*;
+        Ok(match reader.get_bits(MOVE_TAG_BITS)? {
+            0 => CarteBlanche,
+            1 => CarteRouge,
+            2 => Exchange(Hand::get(reader)?),
+            3 => DeclarationCount(CombinationType::get(reader)?, reader.get_varint()?),
+            4 => DeclarationUpper(CombinationType::get(reader)?, Rank::get(reader)?),
+            5 => PlayerResponse(CombinationType::get(reader)?, DeclarationResponse::get(reader)?),
+            6 => Declaration(Combination::get(reader)?),
+            7 => Repique,
+            8 => PlayFirst(Card::get(reader)?),
+            9 => Pique,
+            10 => WinAsSecond,
+            11 => WinLastTrick,
+            12 => PlayCard(Card::get(reader)?),
+            13 => WinCards,
+            14 => Capot,
+            15 => Sink(CombinationType::get(reader)?),
+            _ => return Err(DecodeError::InvalidValue("move")),
+        })
+    }
+}
+
+impl Binary for Move {
+    fn put(&self, writer: &mut BitWriter) {
+        writer.put_bool(self.player() == PlayerId::P2);
+        self.player_move().put(writer);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        let player = if reader.get_bool()? { PlayerId::P2 } else { PlayerId::P1 };
+        Ok(Move::new(player, PlayerMove::get(reader)?))
+    }
+}
+
+impl Binary for GameOptions {
+    fn put(&self, writer: &mut BitWriter) {
+        writer.put_bool(self.carteBlanche);
+        writer.put_bool(self.carteRouge);
+        writer.put_bool(self.competitive);
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        Ok(GameOptions { carteBlanche: reader.get_bool()?
+                       , carteRouge: reader.get_bool()?
+                       , competitive: reader.get_bool()?
+        })
+    }
+}
+
+impl Binary for GameRecord {
+    fn put(&self, writer: &mut BitWriter) {
+        for byte in self.seed.iter() {
+            writer.put_bits(*byte as u32, 8);
+        }
+        self.options.put(writer);
+        writer.put_varint(self.moves.len() as u32);
+        for gmove in self.moves.iter() {
+            gmove.put(writer);
+        }
+    }
+
+    fn get(reader: &mut BitReader) -> Result<Self, DecodeError> {
+        let mut seed = [0; 16];
+        for byte in seed.iter_mut() {
+            *byte = reader.get_bits(8)? as u8;
+        }
+        let options = GameOptions::get(reader)?;
+        let count = reader.get_varint()?;
+        let moves = (0..count).map(|_| Move::get(reader)).collect::<Result<_, _>>()?;
+        Ok(GameRecord { seed, options, moves })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rand_core::SeedableRng;
+
+    fn round_trip<T: Binary + PartialEq + fmt::Debug>(value: T) {
+        assert_eq!(decode::<T>(&encode(&value)), Ok(value));
+    }
+
+    #[test]
+    fn test_cards() {
+        for card in Deck::new().get_cards() {
+            round_trip(card.clone());
+        }
+        let mut deck = Deck::new();
+        deck.shuffle(&mut rand_xorshift::XorShiftRng::from_seed([3; 16]));
+        round_trip(deck.clone());
+        // version byte, count and 5 bits per card
+        assert_eq!(encode(&deck).len(), 1 + (6 + 32 * 5_usize).div_ceil(8));
+        round_trip(Hand::empty_hand());
+        round_trip("7h 10d Ks As".parse::<Hand>().unwrap());
+        round_trip(CardSet::from(&"7h 10d Ks As".parse().unwrap()));
+        assert_eq!(encode(&CardSet::full()).len(), 5);
+    }
+
+    #[test]
+    fn test_moves() {
+        use PlayerMove::*;
+        let moves = vec![
+            CarteBlanche, CarteRouge, Exchange("7h 8c Ad".parse().unwrap()),
+            DeclarationCount(CombinationType::Point, 75), DeclarationCount(CombinationType::Set, 1000),
+            DeclarationUpper(CombinationType::Sequence, Rank::Queen),
+            PlayerResponse(CombinationType::Set, DeclarationResponse::Equals),
+            Declaration(Combination::new(CombinationType::Set, "Kh Kd Ks".parse().unwrap())),
+            Repique, PlayFirst("As".parse().unwrap()), Pique, WinAsSecond, WinLastTrick,
+            PlayCard("7c".parse().unwrap()), WinCards, Capot, Sink(CombinationType::Sequence),
+        ];
+        for pmove in moves {
+            round_trip(Move::new(PlayerId::P2, pmove.clone()));
+            round_trip(pmove);
+        }
+    }
+
+    #[test]
+    fn test_game_record() {
+        let mut game = crate::game::fixtures::dealt_game(9);
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([10; 16]);
+        while !game.is_partie_over() {
+            if game.get_step() == &Step::End {
+                game.deal().unwrap();
+            }
+            let player = game.player_to_play().unwrap();
+            let moves = game.legal_moves(player);
+            let pmove = moves[rng.gen_range(0, moves.len())].clone();
+            game.play(player, pmove).unwrap();
+        }
+        let record = game.get_record();
+        let bytes = encode(&record);
+        assert!(bytes.len() < serde_json::to_vec(&record).unwrap().len() / 10);
+        assert_eq!(decode(&bytes), Ok(record));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut bytes = encode(&"7h 8h".parse::<Hand>().unwrap());
+        assert_eq!(decode::<Hand>(&bytes[..2]), Err(DecodeError::UnexpectedEnd));
+        bytes[0] = 0;
+        assert_eq!(decode::<Hand>(&bytes), Err(DecodeError::UnsupportedVersion(0)));
+        // sink (15) of a fourth combination type, then the first unused tag
+        assert_eq!(decode::<PlayerMove>(&[VERSION, 0x7e]), Err(DecodeError::InvalidValue("combination type")));
+        assert_eq!(decode::<PlayerMove>(&[VERSION, 0x80]), Err(DecodeError::InvalidValue("move")));
+
+        let twice = Hand::new(vec!["7h".parse().unwrap(), "8h".parse().unwrap(), "7h".parse().unwrap()]);
+        assert_eq!(decode::<Hand>(&encode(&twice)), Err(DecodeError::InvalidValue("duplicate card")));
+        let mut deck = Deck::empty_deck();
+        deck.add_cards(twice.cards());
+        assert_eq!(decode::<Deck>(&encode(&deck)), Err(DecodeError::InvalidValue("duplicate card")));
+    }
+}
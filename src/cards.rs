@@ -1,8 +1,31 @@
 use std::fmt;
 use std::cmp::Ordering;
 use std::slice::Iter;
+use std::str::FromStr;
 use serde::{Serialize, Deserialize};
-use rand::{Rng};
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+// -----------  Parsing -----------
+#[derive (Debug, Clone, PartialEq, Eq)]
+pub enum ParseCardError { Empty
+                        , UnknownRank(String)
+                        , UnknownSuit(String)
+                        , MissingSuit(String)
+}
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseCardError::Empty => write!(f, "empty card"),
+            ParseCardError::UnknownRank(s) => write!(f, "unknown rank '{}', expected one of 7 8 9 10 T J V Q D K R A", s),
+            ParseCardError::UnknownSuit(s) => write!(f, "unknown suit '{}', expected one of ♥ ♦ ♠ ♣ h d s c", s),
+            ParseCardError::MissingSuit(s) => write!(f, "missing suit after '{}'", s),
+        }
+    }
+}
+
+impl std::error::Error for ParseCardError {}
 
 // -----------  Suit -----------
 #[derive (Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -24,6 +47,22 @@ impl fmt::Display for Suit {
     }
 }
 
+// accepts "♥" (or "♡"), "h", "H"...
+impl FromStr for Suit {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err(ParseCardError::Empty),
+            "♥" | "♡" | "h" | "H" => Ok(Suit::Heart),
+            "♦" | "♢" | "d" | "D" => Ok(Suit::Diamond),
+            "♠" | "♤" | "s" | "S" => Ok(Suit::Spade),
+            "♣" | "♧" | "c" | "C" => Ok(Suit::Club),
+            other => Err(ParseCardError::UnknownSuit(other.to_string())),
+        }
+    }
+}
+
 impl Suit {
    pub fn iter() -> Iter<'static, Suit> {
         static SUITS: [Suit;  4] = [
@@ -32,7 +71,7 @@ impl Suit {
             Suit::Spade,
             Suit::Club
         ];
-        SUITS.into_iter()
+        SUITS.iter()
     }
 }
 
@@ -71,7 +110,7 @@ impl Rank {
             Rank::King,
             Rank::Ace,
         ];
-        RANKS.into_iter()
+        RANKS.iter()
     }
 
     pub fn succ(&self) -> Option<Self> {
@@ -103,6 +142,26 @@ impl fmt::Display for Rank {
     }
 }
 
+// accepts "10" or "T", and the french letters R (roi), D (dame), V (valet)
+impl FromStr for Rank {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_uppercase().as_str() {
+            "" => Err(ParseCardError::Empty),
+            "7" => Ok(Rank::Seven),
+            "8" => Ok(Rank::Eight),
+            "9" => Ok(Rank::Nine),
+            "10" | "T" => Ok(Rank::Ten),
+            "J" | "V" => Ok(Rank::Jack),
+            "Q" | "D" => Ok(Rank::Queen),
+            "K" | "R" => Ok(Rank::King),
+            "A" => Ok(Rank::Ace),
+            _ => Err(ParseCardError::UnknownRank(s.trim().to_string())),
+        }
+    }
+}
+
 // -----------  Card -----------
 #[derive (Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Card { 
@@ -116,6 +175,44 @@ impl fmt::Display for Card {
     }
 }
 
+// rank followed by suit : "10 ♥", "TH", "10h", "As", "Dd"...
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let suit_start = s.char_indices().last().ok_or(ParseCardError::Empty)?.0;
+        let (rank, suit) = s.split_at(suit_start);
+        if rank.trim().is_empty() {
+            return Err(Rank::from_str(suit).map_or_else(|_| ParseCardError::UnknownRank(s.to_string()), 
+                                                         |_| ParseCardError::MissingSuit(s.to_string())));
+        }
+        let rank = rank.parse()?;
+        let suit = suit.parse()?;
+        Ok(Card::new(rank, suit))
+    }
+}
+
+// Cards separated by spaces or commas, the space between rank and suit being optional
+fn parse_cards(s: &str) -> Result<Vec<Card>, ParseCardError> {
+    let mut cards = vec![];
+    let mut tokens = s.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty());
+    while let Some(token) = tokens.next() {
+        let card = match Card::from_str(token) {
+            Ok(card) => card,
+            Err(err) => {
+                if token.parse::<Rank>().is_err() {
+                    return Err(err);
+                }
+                let suit = tokens.next().ok_or_else(|| ParseCardError::MissingSuit(token.to_string()))?;
+                Card::new(token.parse()?, suit.parse()?)
+            }
+        };
+        cards.push(card);
+    }
+    Ok(cards)
+}
+
 impl Card {
     pub fn new(rank:Rank, suit:Suit) -> Self {
         Card { rank, suit }
@@ -123,6 +220,21 @@ impl Card {
     pub fn point_value(&self) -> u32 {
         self.rank.point_value()
     }
+
+    // position in a CardSet
+    pub fn index(&self) -> u32 {
+        8 * self.suit.clone() as u32 + self.rank.clone() as u32
+    }
+
+    pub fn from_index(index: u32) -> Self {
+        let suit = Suit::iter().nth((index / 8) as usize).expect("Card index out of range");
+        let rank = Rank::iter().nth((index % 8) as usize).expect("Card index out of range");
+        Card::new(rank.clone(), suit.clone())
+    }
+
+    fn bit(&self) -> u32 {
+        1 << self.index()
+    }
 }
 
 // -----------  Deck -----------
@@ -149,24 +261,55 @@ impl Deck {
         cards
     }
 
+    pub fn len(&self) -> usize {
+        let Deck(cards) = self;
+        cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        let Deck(cards) = self;
+        cards.is_empty()
+    }
+
+    pub fn add_cards(&mut self, new_cards: Vec<Card>) {
+        let Deck(cards) = self;
+        cards.extend(new_cards);
+    }
+
     pub fn shuffle<RNG:Rng>(&mut self, rng: &mut RNG) {
-        rng.shuffle(&mut self.0);
+        self.0.shuffle(rng);
     }
 
     pub fn take_n_cards(&mut self, ncards: usize) -> Vec<Card>{
         let Deck(cards) = self;
-        cards.split_off(ncards)
+        let ncards = ncards.min(cards.len());
+        let rest = cards.split_off(ncards);
+        std::mem::replace(cards, rest)
     }
 
     pub fn draw_hands(&mut self,ncards: usize,nhands: usize) -> Vec<Hand>{
         let mut hands = vec![];
-        for i in 0..nhands {
+        for _ in 0..nhands {
             hands.push(Hand::new(self.take_n_cards(ncards)));
         }
         hands
     }
 }
 
+impl FromStr for Deck {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_cards(s).map(Deck)
+    }
+}
+
+impl Default for Deck {
+    fn default() -> Self {
+        Deck::new()
+    }
+}
+
 // -----------  Hand -----------
 #[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Hand(Vec<Card>);
@@ -175,13 +318,22 @@ impl fmt::Display for Hand {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Hand(cards) = self;
         for (count, c) in cards.iter().enumerate() {
-            if count != 0 { write!(f, " "); }
-            write!(f, "{}", c);
+            if count != 0 { write!(f, " ")?; }
+            write!(f, "{}", c)?;
         }
         write!(f, " ")
     }
 }
 
+// "7h 8h 9h", "7 ♥ 8 ♥ 9 ♥"...
+impl FromStr for Hand {
+    type Err = ParseCardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_cards(s).map(Hand)
+    }
+}
+
 impl Hand {
     pub fn empty_hand() -> Self {
         Hand (vec![])
@@ -196,7 +348,7 @@ impl Hand {
         cards
     }
 
-    pub fn iter(&self) -> Iter<Card> {
+    pub fn iter(&self) -> Iter<'_, Card> {
         let Hand(cards) = self;
         cards.iter()
     }
@@ -206,6 +358,30 @@ impl Hand {
         cards.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        let Hand(cards) = self;
+        cards.is_empty()
+    }
+
+    pub fn contains(&self, card: &Card) -> bool {
+        let Hand(cards) = self;
+        cards.contains(card)
+    }
+
+    pub fn push(&mut self, card: Card) {
+        let Hand(cards) = self;
+        cards.push(card);
+    }
+
+    // returns false if the card was not in the hand
+    pub fn remove(&mut self, card: &Card) -> bool {
+        let Hand(cards) = self;
+        match cards.iter().position(|c| c == card) {
+            Some(idx) => { cards.remove(idx); true },
+            None => false
+        }
+    }
+
     pub fn max(&self) -> Option<&Card> {
         let Hand(cards) = self;
         cards.iter().max()
@@ -244,9 +420,143 @@ impl Hand {
     }
 }
 
+// -----------  CardSet -----------
+// Set of cards as a bitmask, bit `8 * suit + rank`, so that iterating gives the cards sorted by suit
+#[derive (Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct CardSet(u32);
+
+impl CardSet {
+    pub fn empty() -> Self {
+        CardSet(0)
+    }
+
+    pub fn full() -> Self {
+        CardSet(u32::MAX)
+    }
+
+    pub fn from_bits(bits: u32) -> Self {
+        CardSet(bits)
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    // all the cards of a suit
+    pub fn suit_mask(suit: &Suit) -> Self {
+        CardSet(0xff << (8 * suit.clone() as u32))
+    }
+
+    // all the cards of a rank
+    pub fn rank_mask(rank: &Rank) -> Self {
+        CardSet(0x0101_0101 << (rank.clone() as u32))
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, card: &Card) -> bool {
+        self.0 & card.bit() != 0
+    }
+
+    pub fn insert(&mut self, card: &Card) {
+        self.0 |= card.bit();
+    }
+
+    pub fn remove(&mut self, card: &Card) {
+        self.0 &= !card.bit();
+    }
+
+    pub fn union(self, other: CardSet) -> Self {
+        CardSet(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: CardSet) -> Self {
+        CardSet(self.0 & other.0)
+    }
+
+    pub fn difference(self, other: CardSet) -> Self {
+        CardSet(self.0 & !other.0)
+    }
+
+    pub fn is_subset(self, other: CardSet) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    pub fn suit(self, suit: &Suit) -> Self {
+        self.intersection(CardSet::suit_mask(suit))
+    }
+
+    // used for declaration
+    pub fn point_value(self) -> u32 {
+        self.iter().map(|c| c.point_value()).sum()
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Card> {
+        let mut bits = self.0;
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                return None;
+            }
+            let index = bits.trailing_zeros();
+            bits &= bits - 1;
+            Some(Card::from_index(index))
+        })
+    }
+}
+
+impl std::ops::BitOr for CardSet {
+    type Output = CardSet;
+    fn bitor(self, other: CardSet) -> CardSet {
+        self.union(other)
+    }
+}
+
+impl std::ops::BitAnd for CardSet {
+    type Output = CardSet;
+    fn bitand(self, other: CardSet) -> CardSet {
+        self.intersection(other)
+    }
+}
+
+impl std::ops::Sub for CardSet {
+    type Output = CardSet;
+    fn sub(self, other: CardSet) -> CardSet {
+        self.difference(other)
+    }
+}
+
+impl From<&Hand> for CardSet {
+    fn from(hand: &Hand) -> Self {
+        let mut set = CardSet::empty();
+        for card in hand.iter() {
+            set.insert(card);
+        }
+        set
+    }
+}
+
+impl From<CardSet> for Hand {
+    fn from(set: CardSet) -> Self {
+        Hand::new(set.iter().collect())
+    }
+}
+
+impl fmt::Display for CardSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Hand::from(*self))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
 
     #[test]
     fn test_deck() {
@@ -254,6 +564,56 @@ mod tests {
         assert_eq!(cards.len(), 32);
     }
 
+    #[test]
+    fn test_parse() {
+        let ten_hearts = Card::new(Rank::Ten, Suit::Heart);
+        for s in ["10 ♥", "10♥", "TH", "10h", " th "].iter() {
+            assert_eq!(s.parse(), Ok(ten_hearts.clone()));
+        }
+        assert_eq!("As".parse(), Ok(Card::new(Rank::Ace, Suit::Spade)));
+        assert_eq!("7c".parse(), Ok(Card::new(Rank::Seven, Suit::Club)));
+        assert_eq!("Dd".parse(), Ok(Card::new(Rank::Queen, Suit::Diamond)));
+        assert_eq!("R ♣".parse(), Ok(Card::new(Rank::King, Suit::Club)));
+        assert_eq!("V♠".parse(), Ok(Card::new(Rank::Jack, Suit::Spade)));
+        for card in Deck::new().get_cards() {
+            assert_eq!(card.to_string().parse(), Ok(card.clone()));
+        }
+
+        assert_eq!("".parse::<Card>(), Err(ParseCardError::Empty));
+        assert_eq!("1h".parse::<Card>(), Err(ParseCardError::UnknownRank("1".to_string())));
+        assert_eq!("10x".parse::<Card>(), Err(ParseCardError::UnknownSuit("x".to_string())));
+        assert_eq!("A".parse::<Card>(), Err(ParseCardError::MissingSuit("A".to_string())));
+
+        let hand = Hand::new(vec![ 
+            Card::new(Rank::Seven, Suit::Heart), 
+            Card::new(Rank::Ten, Suit::Heart), 
+            Card::new(Rank::Queen, Suit::Spade), 
+        ]);
+        assert_eq!("7h 10 ♥, Qs".parse(), Ok(hand.clone()));
+        assert_eq!(hand.to_string().parse(), Ok(hand));
+        assert_eq!("7h 10".parse::<Hand>(), Err(ParseCardError::MissingSuit("10".to_string())));
+        assert_eq!(Deck::new().get_cards().iter().join(" ").parse(), Ok(Deck::new()));
+    }
+
+    #[test]
+    fn test_card_set() {
+        let hand: Hand = "Kd 7h 8s Ks Ac".parse().unwrap();
+        let set = CardSet::from(&hand);
+        assert_eq!(set.len(), 5);
+        assert!(set.contains(&"Ks".parse().unwrap()));
+        assert!(!set.contains(&"Kh".parse().unwrap()));
+        assert_eq!(Hand::from(set), "7h Kd 8s Ks Ac".parse().unwrap());
+        assert_eq!(set.suit(&Suit::Spade), CardSet::from(&"8s Ks".parse().unwrap()));
+        assert_eq!(set & CardSet::rank_mask(&Rank::King), CardSet::from(&"Kd Ks".parse().unwrap()));
+        assert_eq!((set - set.suit(&Suit::Spade)).len(), 3);
+        assert_eq!(set | CardSet::full(), CardSet::full());
+        assert_eq!(set.point_value(), hand.point_value());
+        assert_eq!(CardSet::from(&Hand::new(Deck::new().get_cards().clone())), CardSet::full());
+        for index in 0..32 {
+            assert_eq!(Card::from_index(index).index(), index);
+        }
+    }
+
     #[test]
     fn test_sort_hand() {
         let mut hand = Hand::new(vec![ 
@@ -284,16 +644,3 @@ mod tests {
         );
     }
 }
-
-// TODO
-//
-// instance (Ord a, Binary a) => Binary (OSet a) where
-//     put s = Bin.put (size s) <> mapM_ Bin.put (toList s)
-//     get   = liftM fromList Bin.get
-//
-// instance (Ord a, ToJSON a) => ToJSON (OSet a) where
-//     toJSON = toJSON . toList
-//
-// instance (Ord a, FromJSON a) => FromJSON (OSet a) where
-//     parseJSON = fmap fromList . parseJSON
-//
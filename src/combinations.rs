@@ -1,9 +1,8 @@
 use std::fmt;
-use std::collections::HashMap;
 use std::cmp::Ordering;
 
 use serde::{Serialize, Deserialize};
-use crate::cards::{Hand, Card, Rank, Suit };
+use crate::cards::{Hand, Rank, Suit, CardSet};
 
 #[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CombinationType {
@@ -20,7 +19,7 @@ impl fmt::Display for CombinationType {
     }
 }
 
-#[derive (Debug, Serialize, Deserialize)]
+#[derive (Debug, Clone, Serialize, Deserialize)]
 pub struct Combination { 
     combination_type: CombinationType,
     cards: Hand
@@ -34,7 +33,7 @@ impl fmt::Display for Combination {
 
 impl PartialEq for Combination {
     fn eq(&self, other: &Combination) -> bool {
-        self.combination_type != other.combination_type || ( self.cards == other.cards )
+        self.combination_type == other.combination_type && self.cards == other.cards
     }
 }
 impl Eq for Combination {}
@@ -47,10 +46,10 @@ impl Ord for Combination {
             // by card count, valid for all combination types
             return self.cards.len().cmp(&other.cards.len())
         }
-        return match self.combination_type {
-            CombinationType::Sequence => self.cards.max().cmp(&other.cards.max()),
-            CombinationType::Set      => self.cards.max().cmp(&other.cards.max()),
-            CombinationType::Point    => if self.cards.len() == 0 {
+        match self.combination_type {
+            CombinationType::Sequence => self.max_rank().cmp(&other.max_rank()),
+            CombinationType::Set      => self.max_rank().cmp(&other.max_rank()),
+            CombinationType::Point    => if self.cards.is_empty() {
                 Ordering::Equal
             } else {
                 self.cards.point_value().cmp(&other.cards.point_value())
@@ -77,7 +76,7 @@ impl Combination {
 
     pub fn show_declaration(&self) -> String {
         let Combination { combination_type:ctype, cards } = self;
-        return match ctype {
+        match ctype {
             CombinationType::Point => format!("Point of {}", cards.len()),
             CombinationType::Set => match cards.len() {
                 3 => String::from("Trio"),
@@ -98,7 +97,7 @@ impl Combination {
 
     pub fn show_declaration_complete(&self) -> String {
         let Combination { combination_type:ctype, cards:chand } = self;
-        return match ctype {
+        match ctype {
             CombinationType::Point => format!("{} totaling {}", self.show_declaration(), chand.point_value()),
             CombinationType::Sequence => format!("{} to {}", self.show_declaration(), chand.max().unwrap()),
             CombinationType::Set => format!("{} of {}", self.show_declaration(), chand.max().unwrap()),
@@ -119,60 +118,72 @@ impl Combination {
     pub fn new(combination_type: CombinationType, cards: Hand) -> Self {
         Combination {combination_type, cards}
     }
+
+    pub fn get_type(&self) -> &CombinationType {
+        &self.combination_type
+    }
+
+    pub fn get_cards(&self) -> &Hand {
+        &self.cards
+    }
+
+    pub fn len(&self) -> usize {
+        self.cards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty()
+    }
+
+    // suits don't matter when comparing sequences or sets
+    pub fn max_rank(&self) -> Option<Rank> {
+        self.cards.max().map(|c| c.rank.clone())
+    }
 }
 
 pub fn get_combinations(ctype: &CombinationType, hand: &Hand) -> Vec<Combination> {
-    let mut chand = hand.clone();
+    get_card_set_combinations(ctype, CardSet::from(hand)).into_iter()
+        .map(|cards| Combination::new(ctype.clone(), Hand::from(cards)))
+        .collect()
+}
+
+// Same as get_combinations, without allocating hands
+pub fn get_card_set_combinations(ctype: &CombinationType, cards: CardSet) -> Vec<CardSet> {
     match ctype {
-        CombinationType::Point => {
-            chand.sort_by_suit();
-            let combs_hashmap = chand.iter().fold(HashMap::new(), |mut m, c| { 
-                let key = format!("{}", c.suit);
-                m.entry(key).or_insert(Vec::new()).push(c.clone()); 
-                m 
-            });
-            combs_hashmap.values()
-                .map(|c| Combination::new(ctype.clone(), Hand::new(c.clone()))) 
-                .collect()
-        },
-        CombinationType::Set => {
-            chand.sort_by_rank();
-            let combs_hashmap = chand.iter().fold(HashMap::new(), |mut m, c| { 
-                let key = format!("{}", c.rank);
-                if c.rank > Rank::Nine {
-                    m.entry(key).or_insert(Vec::new()).push(c.clone()); 
-                }
-                m 
-            });
-            combs_hashmap.values()
-                .filter(|&c| c.len() > 2)
-                .map(|c| Combination::new(ctype.clone(), Hand::new(c.clone()))) 
-                .collect()
-        },
+        CombinationType::Point => Suit::iter()
+            .map(|suit| cards.suit(suit))
+            .filter(|comb| !comb.is_empty())
+            .collect(),
+        CombinationType::Set => Rank::iter()
+            .filter(|&rank| rank > &Rank::Nine)
+            .map(|rank| cards.intersection(CardSet::rank_mask(rank)))
+            .filter(|comb| comb.len() > 2)
+            .collect(),
         CombinationType::Sequence => {
-            chand.sort_by_suit();
-            let combs_vec = chand.iter().fold(Vec::new(), |mut acc, c| { 
-                if acc.len() == 0 {
-                    acc.push(vec![c.clone()])
-                } else {
-                    let seqIdx = acc.len() - 1;
-                    let prec = &acc[seqIdx][acc[seqIdx].len() - 1];
-                    if prec.suit == c.suit && prec.rank.succ() == Some(c.rank.clone()) {
-                        acc[seqIdx].push(c.clone());
-                    } else {
-                        acc.push(vec![c.clone()])
+            let mut combs = vec![];
+            for suit in Suit::iter() {
+                let shift = 8 * suit.clone() as u32;
+                let mut ranks = cards.suit(suit).bits() >> shift;
+                while ranks != 0 {
+                    // run of consecutive ranks starting at the lowest one left
+                    let low = ranks.trailing_zeros();
+                    let length = (!(ranks >> low)).trailing_zeros();
+                    let run = ((1 << length) - 1) << low;
+                    if length > 2 {
+                        combs.push(CardSet::from_bits(run << shift));
                     }
+                    ranks &= !run;
                 }
-                acc 
-            });
-            combs_vec.iter()
-                .filter(|&c| c.len() > 2)
-                .map(|c| Combination::new(ctype.clone(), Hand::new(c.clone()))) 
-                .collect()
+            }
+            combs
         },
     }
 }
 
+pub fn get_best_combination(ctype: &CombinationType, hand: &Hand) -> Option<Combination> {
+    get_combinations(ctype, hand).into_iter().max()
+}
+
 pub fn get_smaller_combinations(mcomb: Option<Combination>, combs: Vec<Combination>) -> Vec<Combination> {
     match mcomb {
         None => Vec::new(),
@@ -183,7 +194,7 @@ pub fn get_smaller_combinations(mcomb: Option<Combination>, combs: Vec<Combinati
 }
 
 pub fn is_carte_blanche(hand: Hand) -> bool {
-    let heads = vec![
+    let heads = [
         crate::cards::Rank::King,
         crate::cards::Rank::Queen,
         crate::cards::Rank::Jack,
@@ -194,6 +205,7 @@ pub fn is_carte_blanche(hand: Hand) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cards::Card;
 
     #[test]
     fn test_get_combinations() {
@@ -243,6 +255,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_card_set_combinations() {
+        let cards = |s: &str| CardSet::from(&s.parse::<Hand>().unwrap());
+        let hand = cards("7h 8h 9h Jh Qh Kh Ah 7s 8s Kd Ks Kc");
+        assert_eq!(
+            get_card_set_combinations(&CombinationType::Sequence, hand),
+            vec![cards("7h 8h 9h"), cards("Jh Qh Kh Ah")]
+        );
+        assert_eq!(get_card_set_combinations(&CombinationType::Set, hand), vec![cards("Kh Kd Ks Kc")]);
+        assert_eq!(
+            get_card_set_combinations(&CombinationType::Point, hand),
+            vec![cards("7h 8h 9h Jh Qh Kh Ah"), cards("Kd"), cards("7s 8s Ks"), cards("Kc")]
+        );
+        assert_eq!(get_card_set_combinations(&CombinationType::Sequence, CardSet::full()).len(), 4);
+    }
+
     #[test]
     fn test_carteblanche() {
         let hand = Hand::new(vec![ 
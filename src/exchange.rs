@@ -0,0 +1,154 @@
+// Exchange advisor : ranks the discards by the expected value of the hand after drawing from
+// the talon
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+
+use crate::cards::*;
+use crate::combinations::*;
+use crate::game::{Game, PlayerId, PiquetError, Step};
+
+#[derive (Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeOption { pub discard: Hand
+                          , pub expected_value: f64
+}
+
+// Points of the combinations, as if they were all scored
+fn combinations_value(cards: CardSet) -> f64 {
+    let points = |ctype: CombinationType| -> Vec<usize> {
+        get_card_set_combinations(&ctype, cards).into_iter()
+            .map(|comb| Combination::new(ctype.clone(), Hand::from(comb)).points())
+            .collect()
+    };
+    let best_point = points(CombinationType::Point).into_iter().max().unwrap_or(0);
+    let others: usize = points(CombinationType::Sequence).into_iter().chain(points(CombinationType::Set)).sum();
+    (best_point + others) as f64
+}
+
+// Expected tricks from the high cards, plus the long suits which win once the other cards
+// of the suit are played
+pub(crate) fn tricks_value(cards: CardSet) -> f64 {
+    let high_cards: f64 = cards.iter().map(|card| match card.rank {
+        Rank::Ace => 1.0,
+        Rank::King => 0.6,
+        Rank::Queen => 0.3,
+        Rank::Jack => 0.15,
+        _ => 0.0
+    }).sum();
+    let long_suits: usize = Suit::iter().map(|suit| cards.suit(suit).len().saturating_sub(4)).sum();
+    high_cards + long_suits as f64
+}
+
+// Heuristic value of a hand : combinations points and about two points by expected trick
+// (one for the card led or won as second, and the share of the 10 points for the majority)
+pub fn hand_value(cards: CardSet) -> f64 {
+    combinations_value(cards) + 2.0 * tricks_value(cards)
+}
+
+// Mean value of the hand after discarding and drawing the same number of unseen cards, over
+// all the possible draws when there are no more than `samples` of them, over `samples` random
+// draws otherwise
+fn expected_value<R: Rng>(kept: CardSet, unseen: &mut [Card], count: usize, samples: usize, rng: &mut R) -> f64 {
+    let draws = (0..count).fold(1_u64, |acc, i| acc * (unseen.len() - i) as u64 / (i as u64 + 1));
+    if draws <= samples as u64 {
+        let total: f64 = unseen.iter().cloned().combinations(count)
+            .map(|drawn| hand_value(kept.union(CardSet::from(&Hand::new(drawn)))))
+            .sum();
+        total / draws as f64
+    } else {
+        let total: f64 = (0..samples).map(|_| {
+            let (drawn, _) = unseen.partial_shuffle(rng, count);
+            let drawn = drawn.iter().fold(CardSet::empty(), |mut set, card| { set.insert(card); set });
+            hand_value(kept.union(drawn))
+        }).sum();
+        total / samples.max(1) as f64
+    }
+}
+
+// Every discard of `min_cards` to `max_cards` cards, best first. The cards drawn are taken among
+// the cards neither in the hand nor `known` to be elsewhere.
+pub fn exchange_options<R: Rng>(hand: &Hand, known: CardSet, min_cards: usize, max_cards: usize, samples: usize, rng: &mut R) -> Vec<ExchangeOption> {
+    let cards = CardSet::from(hand);
+    let mut unseen: Vec<Card> = CardSet::full().difference(cards.union(known)).iter().collect();
+    let mut options: Vec<ExchangeOption> = (min_cards..=max_cards)
+        .flat_map(|size| hand.iter().cloned().combinations(size))
+        .map(|discard| {
+            let discard = Hand::new(discard);
+            let kept = cards.difference(CardSet::from(&discard));
+            let value = expected_value(kept, &mut unseen, discard.len(), samples, rng);
+            ExchangeOption { discard, expected_value: value }
+        })
+        .collect();
+    options.sort_by(|a, b| b.expected_value.partial_cmp(&a.expected_value).expect("NaN hand value"));
+    options
+}
+
+// Ranked discards for the player who has to exchange, within the limits of the game
+pub fn advise_exchange<R: Rng>(game: &Game, player: PlayerId, samples: usize, rng: &mut R) -> Result<Vec<ExchangeOption>, PiquetError> {
+    match game.get_step() {
+        Step::ExchangeElder | Step::ExchangeYounger => (),
+        step => return Err(PiquetError::InvalidForStepError(step.clone()))
+    }
+    if game.player_to_play() != Some(player) {
+        return Err(PiquetError::NotYourTurnError);
+    }
+    let (min_cards, max_cards) = game.exchange_limits();
+    let known = game.view_for(player).known_cards().all();
+    Ok(exchange_options(game.get_hand(player), known, min_cards, max_cards, samples, rng))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::SeedableRng;
+    use crate::game::fixtures::dealt_game;
+
+    #[test]
+    fn test_hand_value() {
+        let cards = |s: &str| CardSet::from(&s.parse::<Hand>().unwrap());
+        // point of six, quint, quatorze of aces
+        assert_eq!(combinations_value(cards("7h 8h 9h 10h Jh Ah As Ad Ac")), 6.0 + 15.0 + 14.0);
+        assert!(hand_value(cards("Ah Kh As Ks")) > hand_value(cards("7h 8h 7s 8s")));
+    }
+
+    #[test]
+    fn test_exchange_options() {
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([2; 16]);
+        let hand: Hand = "7h 8h 9h 10h Jh Ah As Ad Kc 7s 8d 7c".parse().unwrap();
+        let options = exchange_options(&hand, CardSet::empty(), 1, 2, 100, &mut rng);
+        assert_eq!(options.len(), 12 + 66);
+        assert!(options.windows(2).all(|w| w[0].expected_value >= w[1].expected_value));
+        // keeps the quint and the aces
+        let keep: Hand = "7h 8h 9h 10h Jh Ah As Ad".parse().unwrap();
+        assert!(options[0].discard.iter().all(|card| !keep.contains(card)));
+
+        // the queen of hearts is the only card which can be drawn
+        let queen: Hand = "Qh".parse().unwrap();
+        let known = CardSet::full().difference(CardSet::from(&hand)).difference(CardSet::from(&queen));
+        let options = exchange_options(&hand, known, 1, 1, 100, &mut rng);
+        for option in options {
+            let drawn = CardSet::from(&hand).difference(CardSet::from(&option.discard)).union(CardSet::from(&queen));
+            assert_eq!(option.expected_value, hand_value(drawn));
+        }
+    }
+
+    #[test]
+    fn test_advise_exchange() {
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([3; 16]);
+        let mut game = dealt_game(4);
+        let elder = game.elder();
+        assert_eq!(advise_exchange(&game, game.younger(), 10, &mut rng), Err(PiquetError::NotYourTurnError));
+
+        let options = advise_exchange(&game, elder, 10, &mut rng).unwrap();
+        let sizes = options.iter().map(|option| option.discard.len());
+        assert_eq!((sizes.clone().min(), sizes.max()), (Some(1), Some(5)));
+        game.play(elder, crate::game::PlayerMove::Exchange(options[0].discard.clone())).unwrap();
+
+        let younger = game.younger();
+        let (_, max_cards) = game.exchange_limits();
+        let options = advise_exchange(&game, younger, 10, &mut rng).unwrap();
+        assert!(options.iter().any(|option| option.discard.is_empty()));
+        assert!(options.iter().all(|option| option.discard.len() <= max_cards));
+    }
+}
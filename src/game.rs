@@ -1,16 +1,40 @@
+// field and method names follow the original haskell implementation
+#![allow(non_snake_case)]
+
 use crate::cards::*;
 use std::fmt;
+use std::cmp::Ordering;
 use serde::{Serialize, Deserialize};
 use rand_core::SeedableRng;
 use rand::Rng;
+use itertools::Itertools;
 
 use crate::combinations::*;
+use crate::view::PlayerView;
+use crate::record::GameRecord;
+
+// cards dealt to each player
+pub const HAND_SIZE: usize = 12;
+
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Deal { One, Two, Three, Four, Five, Six }
 
-#[derive (Debug, PartialEq, Eq, Serialize, Deserialize)]
-enum Deal { One, Two, Three, Four, Five, Six }
+impl Deal {
+    pub fn succ(&self) -> Option<Self> {
+        use Deal::*;
+        match self {
+            One   => Some(Two),
+            Two   => Some(Three),
+            Three => Some(Four),
+            Four  => Some(Five),
+            Five  => Some(Six),
+            Six   => None
+        }
+    }
+}
 
 #[derive (Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-enum Step { Start 
+pub enum Step { Start 
           , Deal
           , ExchangeElder
           , ExchangeYounger 
@@ -58,36 +82,104 @@ impl Step {
           , End                      => None
         }
     }
+
+    // combination type negotiated during a declaration step
+    pub fn combination_type(&self) -> Option<CombinationType> {
+        use Step::*;
+        match self {
+            DeclarePointElder | DeclarePointResponse | SetPointsPointElder | SetPointsPointYounger 
+                => Some(CombinationType::Point),
+            DeclareSequenceElder | DeclareSequenceResponse | SetPointsSequenceElder | SetPointsSequenceYounger 
+                => Some(CombinationType::Sequence),
+            DeclareSetElder | DeclareSetResponse | SetPointsSetElder | SetPointsSetYounger 
+                => Some(CombinationType::Set),
+            _ => None
+        }
+    }
+
+    // steps played by the engine itself, without waiting for a player move
+    pub fn is_automatic(&self) -> bool {
+        use Step::*;
+        matches!(self, SetPointsPointElder | SetPointsSequenceElder | SetPointsSetElder 
+                     | SetPointsPointYounger | SetPointsSequenceYounger | SetPointsSetYounger 
+                     | PlayEnd)
+    }
 }
 
-#[derive (Debug, PartialEq, Eq, Serialize, Deserialize)]
-enum Move { P1Move(PlayerMove), P2Move(PlayerMove) }
+#[derive (Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerId { P1, P2 }
+
+impl PlayerId {
+    pub fn other(self) -> Self {
+        match self {
+            PlayerId::P1 => PlayerId::P2,
+            PlayerId::P2 => PlayerId::P1,
+        }
+    }
+}
+
+
 
-#[derive (Debug, PartialEq, Eq, Serialize, Deserialize)]
-enum DeclarationResponse { Good, NotGood, Equals } 
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Move { P1Move(PlayerMove), P2Move(PlayerMove) }
 
-#[derive (Debug, PartialEq, Eq, Serialize, Deserialize)]
-struct Declaration(Combination);
+impl Move {
+    pub fn new(player: PlayerId, pmove: PlayerMove) -> Self {
+        match player {
+            PlayerId::P1 => Move::P1Move(pmove),
+            PlayerId::P2 => Move::P2Move(pmove),
+        }
+    }
+
+    pub fn player(&self) -> PlayerId {
+        match self {
+            Move::P1Move(_) => PlayerId::P1,
+            Move::P2Move(_) => PlayerId::P2,
+        }
+    }
+
+    pub fn player_move(&self) -> &PlayerMove {
+        match self {
+            Move::P1Move(pmove) | Move::P2Move(pmove) => pmove,
+        }
+    }
+}
+
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeclarationResponse { Good, NotGood, Equals } 
 
-#[derive (Debug, PartialEq, Eq, Serialize, Deserialize)]
-enum PlayerMove { CarteBlanche 
-                , CarteRouge   
-                , Exchange(Hand) 
-                , DeclarationCount(CombinationType, u32)
-                , DeclarationUpper(CombinationType, Rank) 
-                , PlayerResponse(CombinationType, DeclarationResponse)
-                , Declaration(Combination) 
-                , Repique      
-                , PlayFirst(Card) 
-                , Pique        
-                , WinAsSecond  
-                , WinLastTrick 
-                , PlayCard(Card)
-                , WinCards     
-                , Capot        
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerMove { CarteBlanche 
+                    , CarteRouge   
+                    , Exchange(Hand) 
+                    , DeclarationCount(CombinationType, u32)
+                    , DeclarationUpper(CombinationType, Rank) 
+                    , PlayerResponse(CombinationType, DeclarationResponse)
+                    , Declaration(Combination) 
+                    , Repique      
+                    , PlayFirst(Card) 
+                    , Pique        
+                    , WinAsSecond  
+                    , WinLastTrick 
+                    , PlayCard(Card)
+                    , WinCards     
+                    , Capot        
+                    // younger not answering a declaration, to keep the combination hidden : it
+                    // is conceded to elder. Elder sinks a combination by declaring 0 cards.
+                    , Sink(CombinationType)
 }
 
 impl PlayerMove {
+    // moves played by the engine itself
+    pub fn is_automatic(&self) -> bool {
+        use PlayerMove::*;
+        matches!(self, Declaration(_) | Repique | Pique | WinAsSecond | WinLastTrick | WinCards | Capot | CarteRouge)
+    }
+
+    pub fn is_card(&self) -> bool {
+        matches!(self, PlayerMove::PlayFirst(_) | PlayerMove::PlayCard(_))
+    }
+
     pub fn movePoints(&self) -> usize {
         use PlayerMove::*;
         match self {
@@ -107,23 +199,29 @@ impl PlayerMove {
 }
 
 
-#[derive (Debug, PartialEq, Eq, Serialize, Deserialize)]
-enum PiquetError { NotYourTurnError 
-                 , InvalidForStepError(Step) 
-                 , InvalidCombination
-                 , CardNotInHand
-                 , AlreadyConnectedError
-                 , NotConnectedError
-                 , UnknownCommand
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PiquetError { NotYourTurnError 
+                     , InvalidForStepError(Step) 
+                     , InvalidCombination
+                     , InvalidExchange
+                     , CardNotInHand
+                     , MustFollowSuit
+                     , AlreadyConnectedError
+                     , NotConnectedError
+                     , UnknownCommand
+                     , UndoDisabled
+                     , NoSuchMove
 }
 
 
-#[derive (Debug, Serialize, Deserialize)]
-struct Player {
+#[derive (Debug, Clone, Serialize, Deserialize)]
+pub struct Player {
           hand: Hand
         , isElder: bool
         , leftUntilCarteRouge: Hand
         , cardPlayed: Option<Card>
+        , tricksWon: u32
+        , talonSeen: Hand
         , pointCandidate: Option<Combination>
         , sequenceCandidate: Option<Combination>
         , setCandidate: Option<Combination>
@@ -140,6 +238,8 @@ impl Player {
                , isElder: false
                , leftUntilCarteRouge: Hand::empty_hand()
                , cardPlayed: None
+               , tricksWon: 0
+               , talonSeen: Hand::empty_hand()
                , pointCandidate: None
                , sequenceCandidate: None
                , setCandidate: None
@@ -152,17 +252,123 @@ impl Player {
     }
 }
 
+impl Player {
+    fn candidate(&self, ctype: &CombinationType) -> &Option<Combination> {
+        match ctype {
+            CombinationType::Point => &self.pointCandidate,
+            CombinationType::Sequence => &self.sequenceCandidate,
+            CombinationType::Set => &self.setCandidate,
+        }
+    }
+
+    fn candidate_mut(&mut self, ctype: &CombinationType) -> &mut Option<Combination> {
+        match ctype {
+            CombinationType::Point => &mut self.pointCandidate,
+            CombinationType::Sequence => &mut self.sequenceCandidate,
+            CombinationType::Set => &mut self.setCandidate,
+        }
+    }
+}
+
 impl fmt::Display for Player {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{} : {} rougeLeft={} : {}", self.name, self.dealPoints, self.leftUntilCarteRouge.len(), self.hand) 
     }
 }
 
-#[derive (Debug, PartialEq, Eq)]
-enum DeclarationWinner { Elder, Younger, Tie, Nobody }
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeclarationWinner { Elder, Younger, Tie, Nobody }
+
+// Response younger has to give to elder's declaration : only the number of cards is compared 
+// at first, the value of the combinations is compared after an Equals
+pub fn declaration_response(elder: &Option<Combination>, younger: &Option<Combination>, compare_value: bool) -> DeclarationResponse {
+    let ordering = match (elder, younger) {
+        (_, None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (Some(ecomb), Some(ycomb)) => if compare_value {
+            ecomb.cmp(ycomb)
+        } else {
+            ecomb.len().cmp(&ycomb.len())
+        }
+    };
+    match ordering {
+        Ordering::Greater => DeclarationResponse::Good,
+        Ordering::Less => DeclarationResponse::NotGood,
+        Ordering::Equal => DeclarationResponse::Equals,
+    }
+}
+
+// Best combination of the hand backing a declaration of elder : with the number of cards 
+// declared or, after an Equals on `equals_count` cards, with the value declared
+pub fn matching_combination(ctype: &CombinationType, hand: &Hand, pmove: &PlayerMove, equals_count: Option<usize>) -> Option<Combination> {
+    let combinations = get_combinations(ctype, hand).into_iter();
+    match (equals_count, pmove) {
+        (None, PlayerMove::DeclarationCount(_, count)) => combinations
+            .filter(|c| c.len() == *count as usize)
+            .max(),
+        (Some(count), PlayerMove::DeclarationCount(_, total)) => combinations
+            .filter(|c| c.len() == count && c.get_cards().point_value() == *total)
+            .max(),
+        (Some(count), PlayerMove::DeclarationUpper(_, rank)) => combinations
+            .filter(|c| c.len() == count && c.max_rank().as_ref() == Some(rank))
+            .max(),
+        _ => None
+    }
+}
+
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartieResult { pub winner: Option<PlayerId>
+                        , pub player1Points: u32
+                        , pub player2Points: u32
+                        , pub score: u32
+                        , pub rubicon: bool
+}
 
-#[derive (Debug)]
+impl PartieResult {
+    // The winner scores 100 plus the difference of the totals. When the loser has not reached 
+    // 100 points (is rubiconed), the winner scores the sum of both totals, plus 100 if the winner 
+    // has reached 100.
+    pub fn new(player1Points: u32, player2Points: u32) -> Self {
+        let (winner, winner_points, loser_points) = match player1Points.cmp(&player2Points) {
+            Ordering::Greater => (Some(PlayerId::P1), player1Points, player2Points),
+            Ordering::Less => (Some(PlayerId::P2), player2Points, player1Points),
+            Ordering::Equal => (None, player1Points, player2Points),
+        };
+        let rubicon = winner.is_some() && loser_points < 100;
+        let score = if winner.is_none() {
+            0
+        } else if !rubicon {
+            100 + winner_points - loser_points
+        } else if winner_points >= 100 {
+            100 + winner_points + loser_points
+        } else {
+            winner_points + loser_points
+        };
+        PartieResult { winner, player1Points, player2Points, score, rubicon }
+    }
+}
+
+// Optional rules
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameOptions { pub carteBlanche: bool
+                       , pub carteRouge: bool
+                       // no undo during competitive games
+                       , pub competitive: bool
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        GameOptions { carteBlanche: true
+                    , carteRouge: false
+                    , competitive: false
+        }
+    }
+}
+
+#[derive (Debug, Clone)]
 pub struct Game { rng: rand_xorshift::XorShiftRng
+        , seed                : [u8; 16]
+        , options             : GameOptions
         , dealNum             : Deal
         , dealMoves           : Vec<(Move, u32)>
         , deals               : Vec<(Deal, Vec<(Move, u32)>)>
@@ -180,14 +386,22 @@ pub struct Game { rng: rand_xorshift::XorShiftRng
         , sequenceCombination : Option<Combination>
         , setWinner           : DeclarationWinner
         , setCombination      : Option<Combination>
+        // undone moves of the current deal, the next one to redo is last
+        , redoMoves           : Vec<Move>
 }
 
 impl Game {
     pub fn new(seed:[u8; 16]) -> Self {
+        Game::new_with_options(seed, GameOptions::default())
+    }
+
+    pub fn new_with_options(seed:[u8; 16], options: GameOptions) -> Self {
         let mut deck = Deck::new();
         let mut rng = rand_xorshift::XorShiftRng::from_seed(seed);
         deck.shuffle(&mut rng);
         Game { rng
+            , seed
+            , options
             , dealNum: Deal::One
             , dealMoves: vec![]
             , deals: vec![]
@@ -205,6 +419,7 @@ impl Game {
             , sequenceCombination: None
             , setWinner: DeclarationWinner::Nobody
             , setCombination: None
+            , redoMoves: vec![]
         }
     }
 
@@ -213,22 +428,1424 @@ impl Game {
         self.player2.isElder = !self.player1.isElder;
     }
 
-    pub fn deal<'hands>(&mut self){
-        // let hands = self.deck.draw_hands(12, 2);
-        let hands = vec![Hand::new(vec![]), Hand::new(vec![])];
-        self.player1.hand = hands[0];
+    // Deals the cards at the start of the partie or after the end of the previous deal
+    pub fn deal(&mut self) -> Result<(), PiquetError> {
+        match self.step {
+            Step::Start => (),
+            Step::End => {
+                self.dealNum = self.dealNum.succ().ok_or(PiquetError::InvalidForStepError(Step::End))?;
+                // elder and dealer swap at each deal
+                let is_p1_elder = self.elder() == PlayerId::P2;
+                self.player1.isElder = is_p1_elder;
+                self.player2.isElder = !is_p1_elder;
+                self.deck = Deck::new();
+                self.deck.shuffle(&mut self.rng);
+            },
+            _ => return Err(PiquetError::InvalidForStepError(self.step.clone()))
+        }
+
+        let hands = self.deck.draw_hands(HAND_SIZE, 2);
+        self.player1.hand = hands[0].clone();
         self.player1.leftUntilCarteRouge = self.player1.hand.clone();
+        self.player1.cardPlayed = None;
+        self.player1.tricksWon = 0;
+        self.player1.talonSeen = Hand::empty_hand();
         self.player1.pointCandidate = None;
         self.player1.sequenceCandidate = None;
         self.player1.setCandidate = None;
+        self.player1.dealPoints = 0;
 
-        self.player2.hand = hands[1];
+        self.player2.hand = hands[1].clone();
         self.player2.leftUntilCarteRouge = self.player2.hand.clone();
+        self.player2.cardPlayed = None;
+        self.player2.tricksWon = 0;
+        self.player2.talonSeen = Hand::empty_hand();
         self.player2.pointCandidate = None;
         self.player2.sequenceCandidate = None;
         self.player2.setCandidate = None;
+        self.player2.dealPoints = 0;
+
+        self.visible = Deck::empty_deck();
+        self.isElderToPlay = true;
+        self.pointWinner = DeclarationWinner::Nobody;
+        self.pointCombination = None;
+        self.sequenceWinner = DeclarationWinner::Nobody;
+        self.sequenceCombination = None;
+        self.setWinner = DeclarationWinner::Nobody;
+        self.setCombination = None;
         self.dealMoves = vec![];
         self.step = Step::Deal.succ().expect("No more step");
+        Ok(())
+    }
+
+    // A game consistent with what the player sees, the hidden cards being given : used by bots 
+    // to simulate the rest of the deal
+    pub fn from_view(view: &PlayerView, options: GameOptions, opponent_hand: Hand, opponent_discard: Hand, talon: Deck, seed: [u8; 16]) -> Self {
+        let mut game = Game::new_with_options(seed, options);
+        let me = view.player;
+        let opponent = me.other();
+        game.dealNum = view.dealNum.clone();
+        game.step = view.step.clone();
+        game.deck = talon;
+        game.visible = view.visible.clone();
+        game.dealMoves = view.dealMoves.iter()
+            .map(|gmove| (gmove.clone(), gmove.player_move().movePoints() as u32))
+            .collect();
+        if view.opponentDiscardCount.is_some() {
+            // elder exchanges first
+            let own_exchange = game.dealMoves.iter()
+                .position(|(gmove, _)| matches!(gmove.player_move(), PlayerMove::Exchange(_)));
+            let idx = match own_exchange {
+                Some(idx) if view.isElder => idx + 1,
+                Some(idx) => idx,
+                None => game.dealMoves.len()
+            };
+            game.dealMoves.insert(idx, (Move::new(opponent, PlayerMove::Exchange(opponent_discard)), 0));
+        }
+
+        let current = game.player_mut(me);
+        current.isElder = view.isElder;
+        current.hand = view.hand.clone();
+        current.leftUntilCarteRouge = view.hand.clone();
+        current.cardPlayed = view.cardPlayed.clone();
+        current.tricksWon = view.tricksWon;
+        current.talonSeen = view.talonSeen.clone();
+        current.dealPoints = view.dealPoints;
+        current.gamePoints = view.gamePoints;
+        let other = game.player_mut(opponent);
+        other.isElder = !view.isElder;
+        other.leftUntilCarteRouge = opponent_hand.clone();
+        other.hand = opponent_hand;
+        other.cardPlayed = view.opponentCardPlayed.clone();
+        other.tricksWon = view.opponentTricksWon;
+        other.dealPoints = view.opponentDealPoints;
+        other.gamePoints = view.opponentGamePoints;
+        game.isElderToPlay = view.playerToPlay.is_none_or(|player| player == game.elder());
+
+        // declared combinations, found as the engine does in the hands before the cards played
+        let mut value_stages = vec![];
+        for gmove in view.dealMoves.iter() {
+            let player = gmove.player();
+            let hand = view.before_play(player, game.get_hand(player));
+            let pmove = gmove.player_move();
+            let (ctype, candidate) = match pmove {
+                PlayerMove::DeclarationCount(ctype, _) | PlayerMove::DeclarationUpper(ctype, _) => {
+                    let equals_count = if value_stages.contains(ctype) {
+                        Some(game.player(player).candidate(ctype).as_ref().map_or(0, |c| c.len()))
+                    } else {
+                        None
+                    };
+                    (ctype, matching_combination(ctype, &hand, pmove, equals_count))
+                },
+                PlayerMove::PlayerResponse(ctype, response) => {
+                    if response == &DeclarationResponse::Equals {
+                        value_stages.push(ctype.clone());
+                    }
+                    (ctype, get_best_combination(ctype, &hand))
+                },
+                PlayerMove::Sink(ctype) => (ctype, None),
+                _ => continue
+            };
+            *game.player_mut(player).candidate_mut(ctype) = candidate;
+        }
+        let winners = [(CombinationType::Point, &view.pointWinner), (CombinationType::Sequence, &view.sequenceWinner), (CombinationType::Set, &view.setWinner)];
+        for (ctype, winner) in winners.iter() {
+            let player = match winner {
+                DeclarationWinner::Elder => game.elder(),
+                DeclarationWinner::Younger => game.younger(),
+                _ => {
+                    game.set_declaration_winner(ctype, (*winner).clone(), None);
+                    continue
+                }
+            };
+            match game.player(player).candidate(ctype).clone() {
+                Some(comb) => game.set_declaration_winner(ctype, (*winner).clone(), Some(comb)),
+                None => game.set_declaration_winner(ctype, DeclarationWinner::Nobody, None),
+            }
+        }
+        game
+    }
+
+    pub fn get_seed(&self) -> &[u8; 16] {
+        &self.seed
+    }
+
+    pub fn get_options(&self) -> &GameOptions {
+        &self.options
+    }
+
+    // Moves sent by the players since the start of the partie
+    pub fn get_player_moves(&self) -> Vec<Move> {
+        let current: &[(Move, u32)] = if self.step == Step::End { &[] } else { &self.dealMoves };
+        self.deals.iter()
+            .flat_map(|(_, moves)| moves.iter())
+            .chain(current.iter())
+            .map(|(gmove, _)| gmove)
+            .filter(|gmove| !gmove.player_move().is_automatic())
+            .cloned()
+            .collect()
+    }
+
+    pub fn get_record(&self) -> GameRecord {
+        GameRecord::new(self)
+    }
+
+    // Moves sent by the players during the current deal, followed by the undone ones
+    fn deal_line(&self) -> Vec<Move> {
+        self.dealMoves.iter()
+            .map(|(gmove, _)| gmove)
+            .filter(|gmove| !gmove.player_move().is_automatic())
+            .chain(self.redoMoves.iter().rev())
+            .cloned()
+            .collect()
+    }
+
+    // Number of moves sent by the players during the current deal
+    pub fn get_move_index(&self) -> usize {
+        self.deal_line().len() - self.redoMoves.len()
+    }
+
+    // State of the current deal after the first `move_index` moves sent by the players, 
+    // rebuilt by replaying the game from its seed. Moves after the index can be redone.
+    // Disabled in competitive games, as it shows the earlier positions of the deal.
+    pub fn state_at(&self, move_index: usize) -> Result<Game, PiquetError> {
+        if self.options.competitive {
+            return Err(PiquetError::UndoDisabled);
+        }
+        let line = self.deal_line();
+        if move_index > line.len() {
+            return Err(PiquetError::NoSuchMove);
+        }
+        let mut record = self.get_record();
+        let previous = record.moves.len() - self.get_move_index();
+        record.moves.truncate(previous);
+        record.moves.extend(line[..move_index].iter().cloned());
+        let mut game = record.replay().map_err(|(_, e)| e)?;
+        if game.dealNum != self.dealNum {
+            // replay stops at the end of the previous deal
+            game.deal()?;
+        }
+        game.redoMoves = line[move_index..].iter().rev().cloned().collect();
+        Ok(game)
+    }
+
+    // Cancels the last move sent by a player during the current deal
+    pub fn undo(&mut self) -> Result<(), PiquetError> {
+        if self.options.competitive {
+            return Err(PiquetError::UndoDisabled);
+        }
+        let move_index = self.get_move_index();
+        if move_index == 0 {
+            return Err(PiquetError::NoSuchMove);
+        }
+        *self = self.state_at(move_index - 1)?;
+        Ok(())
+    }
+
+    // Plays again the last undone move
+    pub fn redo(&mut self) -> Result<(), PiquetError> {
+        let mut redo_moves = self.redoMoves.clone();
+        let gmove = redo_moves.pop().ok_or(PiquetError::NoSuchMove)?;
+        self.play(gmove.player(), gmove.player_move().clone())?;
+        self.redoMoves = redo_moves;
+        Ok(())
+    }
+
+    pub fn get_deal_num(&self) -> &Deal {
+        &self.dealNum
+    }
+
+    pub fn get_deal_points(&self, player: PlayerId) -> u32 {
+        self.player(player).dealPoints
+    }
+
+    pub fn get_game_points(&self, player: PlayerId) -> u32 {
+        self.player(player).gamePoints
+    }
+
+    pub fn get_deals(&self) -> &Vec<(Deal, Vec<(Move, u32)>)> {
+        &self.deals
+    }
+
+    pub fn is_partie_over(&self) -> bool {
+        self.step == Step::End && self.dealNum == Deal::Six
+    }
+
+    pub fn get_partie_result(&self) -> Option<PartieResult> {
+        if self.is_partie_over() {
+            Some(PartieResult::new(self.player1.gamePoints, self.player2.gamePoints))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_step(&self) -> &Step {
+        &self.step
+    }
+
+    pub fn get_hand(&self, player: PlayerId) -> &Hand {
+        &self.player(player).hand
+    }
+
+    pub fn get_visible(&self) -> &Deck {
+        &self.visible
+    }
+
+    pub fn get_talon(&self) -> &Deck {
+        &self.deck
+    }
+
+    pub fn get_card_played(&self, player: PlayerId) -> &Option<Card> {
+        &self.player(player).cardPlayed
+    }
+
+    pub fn get_talon_seen(&self, player: PlayerId) -> &Hand {
+        &self.player(player).talonSeen
+    }
+
+    pub fn get_player_name(&self, player: PlayerId) -> &str {
+        &self.player(player).name
+    }
+
+    // What the player is allowed to know about the game
+    pub fn view_for(&self, player: PlayerId) -> PlayerView {
+        PlayerView::new(self, player)
+    }
+
+    pub fn get_deal_moves(&self) -> &Vec<(Move, u32)> {
+        &self.dealMoves
+    }
+
+    pub fn elder(&self) -> PlayerId {
+        if self.player1.isElder { PlayerId::P1 } else { PlayerId::P2 }
+    }
+
+    pub fn younger(&self) -> PlayerId {
+        self.elder().other()
+    }
+
+    // The player expected to send the next move, None when the game is not waiting for anyone
+    pub fn player_to_play(&self) -> Option<PlayerId> {
+        use Step::*;
+        match self.step {
+            ExchangeElder | DeclarePointElder | DeclareSequenceElder | DeclareSetElder | PlayFirstCard 
+                => Some(self.elder()),
+            ExchangeYounger | DeclarePointResponse | DeclareSequenceResponse | DeclareSetResponse 
+                => Some(self.younger()),
+            PlayCards => Some(if self.isElderToPlay { self.elder() } else { self.younger() }),
+            _ => None
+        }
+    }
+
+    // Single entry point for the players : checks the move against the current step, applies it
+    // and returns the moves recorded, including the ones automatically played by the engine
+    pub fn play(&mut self, player: PlayerId, pmove: PlayerMove) -> Result<Vec<Move>, PiquetError> {
+        self.check_move(player, &pmove)?;
+        self.redoMoves.clear();
+        if pmove == PlayerMove::CarteBlanche {
+            return Ok(self.play_carte_blanche(player));
+        }
+
+        use Step::*;
+        Ok(match self.step {
+            ExchangeElder | ExchangeYounger => self.play_exchange(player, pmove),
+            DeclarePointElder | DeclareSequenceElder | DeclareSetElder 
+                => self.play_elder_declaration(player, pmove),
+            DeclarePointResponse | DeclareSequenceResponse | DeclareSetResponse 
+                => self.play_declaration_response(player, pmove),
+            _ => self.play_card(player, pmove),
+        })
+    }
+
+    // Checks that the move is valid for the player in the current state of the game, without 
+    // playing it
+    pub fn check_move(&self, player: PlayerId, pmove: &PlayerMove) -> Result<(), PiquetError> {
+        if pmove == &PlayerMove::CarteBlanche {
+            // may be declared by younger while elder is exchanging
+            return self.check_carte_blanche(player);
+        }
+        match self.player_to_play() {
+            None => return Err(PiquetError::InvalidForStepError(self.step.clone())),
+            Some(expected) if expected != player => return Err(PiquetError::NotYourTurnError),
+            _ => ()
+        }
+        if !self.is_move_for_step(pmove) {
+            return Err(PiquetError::InvalidForStepError(self.step.clone()));
+        }
+
+        match pmove {
+            PlayerMove::Exchange(discard) => self.check_exchange(player, discard),
+            PlayerMove::DeclarationCount(ctype, _) | PlayerMove::DeclarationUpper(ctype, _) 
+                => self.declared_combination(ctype, pmove).map(|_| ()),
+            PlayerMove::PlayerResponse(ctype, response) => self.check_declaration_response(player, ctype, response),
+            PlayerMove::Sink(_) => Ok(()),
+            PlayerMove::PlayFirst(card) | PlayerMove::PlayCard(card) => self.check_card(player, card),
+            _ => Err(PiquetError::InvalidForStepError(self.step.clone()))
+        }
+    }
+
+    // All the moves the player can send in the current state of the game
+    pub fn legal_moves(&self, player: PlayerId) -> Vec<PlayerMove> {
+        self.candidate_moves(player).into_iter()
+            .filter(|pmove| self.check_move(player, pmove).is_ok())
+            .collect()
+    }
+
+    // Moves to be checked by legal_moves
+    fn candidate_moves(&self, player: PlayerId) -> Vec<PlayerMove> {
+        let mut moves = vec![PlayerMove::CarteBlanche];
+        if self.player_to_play() != Some(player) {
+            return moves;
+        }
+
+        use Step::*;
+        let hand = self.get_hand(player);
+        match self.step {
+            ExchangeElder | ExchangeYounger => {
+                let (min_cards, max_cards) = self.exchange_limits();
+                for size in min_cards..=max_cards {
+                    moves.extend(hand.iter().cloned().combinations(size)
+                                 .map(|cards| PlayerMove::Exchange(Hand::new(cards))));
+                }
+            },
+            DeclarePointElder | DeclareSequenceElder | DeclareSetElder => {
+                let ctype = self.step.combination_type().expect("Not a declaration step");
+                moves.push(PlayerMove::DeclarationCount(ctype.clone(), 0));
+                for comb in get_combinations(&ctype, hand) {
+                    let count = PlayerMove::DeclarationCount(ctype.clone(), comb.len() as u32);
+                    let value = match ctype {
+                        CombinationType::Point => PlayerMove::DeclarationCount(ctype.clone(), comb.get_cards().point_value()),
+                        _ => PlayerMove::DeclarationUpper(ctype.clone(), comb.max_rank().expect("Empty combination")),
+                    };
+                    for pmove in [count, value] {
+                        if !moves.contains(&pmove) {
+                            moves.push(pmove);
+                        }
+                    }
+                }
+            },
+            DeclarePointResponse | DeclareSequenceResponse | DeclareSetResponse => {
+                let ctype = self.step.combination_type().expect("Not a declaration step");
+                for response in [DeclarationResponse::Good, DeclarationResponse::NotGood, DeclarationResponse::Equals].iter() {
+                    moves.push(PlayerMove::PlayerResponse(ctype.clone(), response.clone()));
+                }
+                moves.push(PlayerMove::Sink(ctype));
+            },
+            PlayFirstCard | PlayCards => {
+                for card in hand.iter() {
+                    moves.push(PlayerMove::PlayFirst(card.clone()));
+                    moves.push(PlayerMove::PlayCard(card.clone()));
+                }
+            },
+            _ => ()
+        }
+        moves
+    }
+
+    fn is_move_for_step(&self, pmove: &PlayerMove) -> bool {
+        use Step::*;
+        let step_ctype = self.step.combination_type();
+        match (&self.step, pmove) {
+            (ExchangeElder, PlayerMove::Exchange(_)) 
+                | (ExchangeYounger, PlayerMove::Exchange(_)) => true,
+            (DeclarePointElder, PlayerMove::DeclarationCount(ctype, _)) 
+                | (DeclareSequenceElder, PlayerMove::DeclarationCount(ctype, _)) 
+                | (DeclareSetElder, PlayerMove::DeclarationCount(ctype, _)) 
+                | (DeclareSequenceElder, PlayerMove::DeclarationUpper(ctype, _)) 
+                | (DeclareSetElder, PlayerMove::DeclarationUpper(ctype, _)) 
+                | (DeclarePointResponse, PlayerMove::PlayerResponse(ctype, _)) 
+                | (DeclareSequenceResponse, PlayerMove::PlayerResponse(ctype, _)) 
+                | (DeclareSetResponse, PlayerMove::PlayerResponse(ctype, _)) 
+                | (DeclarePointResponse, PlayerMove::Sink(ctype)) 
+                | (DeclareSequenceResponse, PlayerMove::Sink(ctype)) 
+                | (DeclareSetResponse, PlayerMove::Sink(ctype)) 
+                => step_ctype.as_ref() == Some(ctype),
+            (PlayFirstCard, PlayerMove::PlayFirst(_)) => true,
+            (PlayCards, PlayerMove::PlayFirst(_)) => self.is_trick_start(),
+            (PlayCards, PlayerMove::PlayCard(_)) => !self.is_trick_start(),
+            _ => false
+        }
+    }
+
+    fn is_trick_start(&self) -> bool {
+        self.player1.cardPlayed.is_none() && self.player2.cardPlayed.is_none()
+    }
+
+    fn player(&self, player: PlayerId) -> &Player {
+        match player {
+            PlayerId::P1 => &self.player1,
+            PlayerId::P2 => &self.player2,
+        }
+    }
+
+    fn player_mut(&mut self, player: PlayerId) -> &mut Player {
+        match player {
+            PlayerId::P1 => &mut self.player1,
+            PlayerId::P2 => &mut self.player2,
+        }
+    }
+
+    // Adds the move to the deal history and scores its points
+    fn record(&mut self, player: PlayerId, pmove: PlayerMove) -> Move {
+        let points = pmove.movePoints() as u32;
+        self.player_mut(player).dealPoints += points;
+        let gmove = Move::new(player, pmove);
+        self.dealMoves.push((gmove.clone(), points));
+        gmove
+    }
+
+    // Goes to the next step, playing the automatic steps on the way
+    fn next_step(&mut self) -> Vec<Move> {
+        let mut moves = vec![];
+        self.step = self.step.succ().expect("No more step");
+        while self.step.is_automatic() {
+            moves.append(&mut self.play_automatic_step());
+            self.step = self.step.succ().expect("No more step");
+        }
+        if self.step == Step::End {
+            self.end_deal();
+        }
+        moves
+    }
+
+    fn end_deal(&mut self) {
+        self.deals.push((self.dealNum.clone(), self.dealMoves.clone()));
+        self.player1.gamePoints += self.player1.dealPoints;
+        self.player2.gamePoints += self.player2.dealPoints;
+        match self.player1.dealPoints.cmp(&self.player2.dealPoints) {
+            Ordering::Greater => self.player1.dealWons += 1,
+            Ordering::Less => self.player2.dealWons += 1,
+            Ordering::Equal => ()
+        }
+    }
+
+    fn play_automatic_step(&mut self) -> Vec<Move> {
+        use Step::*;
+        match self.step {
+            SetPointsPointElder | SetPointsSequenceElder 
+                => self.set_declaration_points(DeclarationWinner::Elder),
+            SetPointsSetElder => {
+                let mut moves = self.set_declaration_points(DeclarationWinner::Elder);
+                moves.append(&mut self.check_repique());
+                moves
+            },
+            SetPointsPointYounger | SetPointsSequenceYounger 
+                => self.set_declaration_points(DeclarationWinner::Younger),
+            SetPointsSetYounger => {
+                let mut moves = self.set_declaration_points(DeclarationWinner::Younger);
+                moves.append(&mut self.check_pique());
+                moves
+            },
+            PlayEnd => self.set_cards_points(),
+            _ => vec![]
+        }
+    }
+
+    pub fn get_declaration_winner(&self, ctype: &CombinationType) -> &DeclarationWinner {
+        match ctype {
+            CombinationType::Point => &self.pointWinner,
+            CombinationType::Sequence => &self.sequenceWinner,
+            CombinationType::Set => &self.setWinner,
+        }
+    }
+
+    pub fn get_declaration_combination(&self, ctype: &CombinationType) -> &Option<Combination> {
+        match ctype {
+            CombinationType::Point => &self.pointCombination,
+            CombinationType::Sequence => &self.sequenceCombination,
+            CombinationType::Set => &self.setCombination,
+        }
+    }
+
+    fn set_declaration_winner(&mut self, ctype: &CombinationType, winner: DeclarationWinner, combination: Option<Combination>) {
+        match ctype {
+            CombinationType::Point => {
+                self.pointWinner = winner;
+                self.pointCombination = combination;
+            },
+            CombinationType::Sequence => {
+                self.sequenceWinner = winner;
+                self.sequenceCombination = combination;
+            },
+            CombinationType::Set => {
+                self.setWinner = winner;
+                self.setCombination = combination;
+            },
+        }
+    }
+
+    // A carte blanche is declared before exchanging, and proven by showing the hand
+    fn check_carte_blanche(&self, player: PlayerId) -> Result<(), PiquetError> {
+        let before_exchange = match self.step {
+            Step::ExchangeElder => true,
+            Step::ExchangeYounger => player == self.younger(),
+            _ => false
+        };
+        let declared = self.dealMoves.iter().any(|(gmove, _)| gmove == &Move::new(player, PlayerMove::CarteBlanche));
+        if !self.options.carteBlanche || !before_exchange || declared {
+            return Err(PiquetError::InvalidForStepError(self.step.clone()));
+        }
+        if !is_carte_blanche(self.get_hand(player).clone()) {
+            return Err(PiquetError::InvalidCombination);
+        }
+        Ok(())
+    }
+
+    fn play_carte_blanche(&mut self, player: PlayerId) -> Vec<Move> {
+        let shown = self.get_hand(player).clone().cards();
+        self.visible.add_cards(shown);
+        vec![self.record(player, PlayerMove::CarteBlanche)]
+    }
+
+    // A carte rouge is scored when every card of the hand is part of a scored combination
+    fn check_carte_rouge(&mut self, player: PlayerId, scored: &[Combination]) -> Vec<Move> {
+        let current = self.player_mut(player);
+        if current.leftUntilCarteRouge.is_empty() {
+            // already scored
+            return vec![];
+        }
+        for comb in scored {
+            for card in comb.get_cards().iter() {
+                current.leftUntilCarteRouge.remove(card);
+            }
+        }
+        if self.options.carteRouge && self.player(player).leftUntilCarteRouge.is_empty() {
+            vec![self.record(player, PlayerMove::CarteRouge)]
+        } else {
+            vec![]
+        }
+    }
+
+    // The winner of a declaration scores the winning combination and, for sequences and sets, 
+    // all the other combinations of the same type in the winner's hand
+    fn scored_combinations(&self, ctype: &CombinationType) -> Option<(PlayerId, Vec<Combination>)> {
+        let player = match self.get_declaration_winner(ctype) {
+            DeclarationWinner::Elder => self.elder(),
+            DeclarationWinner::Younger => self.younger(),
+            _ => return None
+        };
+        let winning = self.get_declaration_combination(ctype).clone().expect("No winning combination");
+        let mut scored = vec![winning.clone()];
+        if ctype != &CombinationType::Point {
+            scored.extend(get_combinations(ctype, self.get_hand(player)).into_iter().filter(|c| c != &winning));
+        }
+        Some((player, scored))
+    }
+
+    fn set_declaration_points(&mut self, winner: DeclarationWinner) -> Vec<Move> {
+        let ctype = self.step.combination_type().expect("Not a declaration step");
+        if self.get_declaration_winner(&ctype) != &winner {
+            return vec![];
+        }
+        let (player, scored) = self.scored_combinations(&ctype).expect("No declaration winner");
+        let mut moves: Vec<Move> = scored.iter()
+            .map(|comb| self.record(player, PlayerMove::Declaration(comb.clone())))
+            .collect();
+        moves.append(&mut self.check_carte_rouge(player, &scored));
+        moves
+    }
+
+    fn has_bonus(&self, player: PlayerId) -> bool {
+        self.dealMoves.iter().any(|(gmove, _)| gmove.player() == player 
+                                  && (gmove.player_move() == &PlayerMove::Repique || gmove.player_move() == &PlayerMove::Pique))
+    }
+
+    // A repique is scored by reaching thirty with combinations alone, reckoned in order (carte 
+    // blanche, point, sequence, set), before the opponent scores anything. It is checked as soon 
+    // as the declarations are settled, before younger's combinations are actually counted.
+    fn check_repique(&mut self) -> Vec<Move> {
+        let mut scores: Vec<(PlayerId, u32)> = self.dealMoves.iter()
+            .filter(|(gmove, _)| gmove.player_move() == &PlayerMove::CarteBlanche)
+            .map(|(gmove, points)| (gmove.player(), *points))
+            .collect();
+        for ctype in [CombinationType::Point, CombinationType::Sequence, CombinationType::Set].iter() {
+            if let Some((player, combinations)) = self.scored_combinations(ctype) {
+                scores.extend(combinations.iter().map(|comb| (player, comb.points() as u32)));
+            }
+        }
+
+        let (mut p1_points, mut p2_points) = (0, 0);
+        for (player, points) in scores {
+            match player {
+                PlayerId::P1 => p1_points += points,
+                PlayerId::P2 => p2_points += points,
+            }
+            let winner = if p1_points >= 30 && p2_points == 0 {
+                PlayerId::P1
+            } else if p2_points >= 30 && p1_points == 0 {
+                PlayerId::P2
+            } else {
+                continue
+            };
+            return vec![self.record(winner, PlayerMove::Repique)];
+        }
+        vec![]
+    }
+
+    // Elder scores a pique by reaching thirty with combinations and play before younger scores 
+    // anything. The points for the cards and the capot don't count, and a repique excludes it.
+    fn check_pique(&mut self) -> Vec<Move> {
+        let elder = self.elder();
+        if self.player(elder.other()).dealPoints > 0 
+            || self.player(elder).dealPoints < 30 
+            || self.has_bonus(elder) {
+            return vec![];
+        }
+        vec![self.record(elder, PlayerMove::Pique)]
+    }
+
+    // Elder must exchange between one and five cards, younger may take up to all the remaining 
+    // talon. Cards left by elder are available to younger ; cards left by younger are seen by 
+    // younger only, then shown to both players once elder has led the first trick.
+    fn check_exchange(&self, player: PlayerId, discard: &Hand) -> Result<(), PiquetError> {
+        let (min_cards, max_cards) = self.exchange_limits();
+        if discard.len() < min_cards || discard.len() > max_cards {
+            return Err(PiquetError::InvalidExchange);
+        }
+        let mut hand = self.get_hand(player).clone();
+        for card in discard.iter() {
+            if !hand.remove(card) {
+                return Err(PiquetError::CardNotInHand);
+            }
+        }
+        Ok(())
+    }
+
+    fn play_exchange(&mut self, player: PlayerId, pmove: PlayerMove) -> Vec<Move> {
+        let discard = match &pmove {
+            PlayerMove::Exchange(discard) => discard.clone(),
+            _ => panic!("Not an exchange")
+        };
+        let mut hand = self.get_hand(player).clone();
+        for card in discard.iter() {
+            hand.remove(card);
+        }
+
+        for card in self.deck.take_n_cards(discard.len()) {
+            hand.push(card);
+        }
+        let talon = self.deck.get_cards();
+        let seen = if self.step == Step::ExchangeElder {
+            // elder may look at the cards left for younger among the first five
+            let left = (5 - discard.len()).min(talon.len());
+            Hand::new(talon[..left].to_vec())
+        } else {
+            Hand::new(talon.clone())
+        };
+        let current = self.player_mut(player);
+        current.leftUntilCarteRouge = hand.clone();
+        current.hand = hand;
+        current.talonSeen = seen;
+
+        let mut moves = vec![self.record(player, pmove)];
+        moves.append(&mut self.next_step());
+        moves
+    }
+
+    // Minimum and maximum number of cards the current player can exchange
+    pub fn exchange_limits(&self) -> (usize, usize) {
+        match self.step {
+            Step::ExchangeElder => (1, 5.min(self.deck.len())),
+            Step::ExchangeYounger => (0, self.deck.len()),
+            _ => (0, 0)
+        }
+    }
+
+    // After an Equals on the number of cards, elder has to tell the value of the combination
+    fn is_value_declaration(&self, ctype: &CombinationType) -> bool {
+        let equals = PlayerMove::PlayerResponse(ctype.clone(), DeclarationResponse::Equals);
+        self.dealMoves.iter().any(|(gmove, _)| gmove.player_move() == &equals)
+    }
+
+    // Finds the combination of elder's hand backing the declaration
+    fn declared_combination(&self, ctype: &CombinationType, pmove: &PlayerMove) -> Result<Option<Combination>, PiquetError> {
+        let elder = self.player(self.elder());
+        let equals_count = if self.is_value_declaration(ctype) {
+            Some(elder.candidate(ctype).as_ref().map_or(0, |c| c.len()))
+        } else {
+            None
+        };
+        match (equals_count, pmove) {
+            (None, PlayerMove::DeclarationCount(_, 0)) => return Ok(None),
+            (None, PlayerMove::DeclarationCount(_, _)) => (),
+            (Some(_), PlayerMove::DeclarationCount(CombinationType::Point, _)) => (),
+            (Some(_), PlayerMove::DeclarationUpper(_, _)) if ctype != &CombinationType::Point => (),
+            _ => return Err(PiquetError::InvalidForStepError(self.step.clone()))
+        }
+        match matching_combination(ctype, &elder.hand, pmove, equals_count) {
+            None => Err(PiquetError::InvalidCombination),
+            some => Ok(some)
+        }
+    }
+
+    fn play_elder_declaration(&mut self, player: PlayerId, pmove: PlayerMove) -> Vec<Move> {
+        let ctype = self.step.combination_type().expect("Not a declaration step");
+        let candidate = self.declared_combination(&ctype, &pmove).expect("Invalid declaration");
+        *self.player_mut(player).candidate_mut(&ctype) = candidate;
+        let mut moves = vec![self.record(player, pmove)];
+        moves.append(&mut self.next_step());
+        moves
+    }
+
+    fn check_declaration_response(&self, player: PlayerId, ctype: &CombinationType, response: &DeclarationResponse) -> Result<(), PiquetError> {
+        let elder_candidate = self.player(self.elder()).candidate(ctype);
+        let younger_candidate = get_best_combination(ctype, self.get_hand(player));
+        let compare_value = self.is_value_declaration(ctype);
+        if response != &declaration_response(elder_candidate, &younger_candidate, compare_value) {
+            return Err(PiquetError::InvalidCombination);
+        }
+        Ok(())
+    }
+
+    fn play_declaration_response(&mut self, player: PlayerId, pmove: PlayerMove) -> Vec<Move> {
+        let ctype = self.step.combination_type().expect("Not a declaration step");
+        let elder_candidate = self.player(self.elder()).candidate(&ctype).clone();
+        // sinking concedes the declaration to elder
+        let (response, younger_candidate) = match &pmove {
+            PlayerMove::PlayerResponse(_, response) => (response.clone(), get_best_combination(&ctype, self.get_hand(player))),
+            PlayerMove::Sink(_) => (DeclarationResponse::Good, None),
+            _ => panic!("Not a declaration response")
+        };
+        let compare_value = self.is_value_declaration(&ctype);
+
+        *self.player_mut(player).candidate_mut(&ctype) = younger_candidate.clone();
+        let mut moves = vec![self.record(player, pmove)];
+        if response == DeclarationResponse::Equals && !compare_value {
+            self.step = match ctype {
+                CombinationType::Point => Step::DeclarePointElder,
+                CombinationType::Sequence => Step::DeclareSequenceElder,
+                CombinationType::Set => Step::DeclareSetElder,
+            };
+            return moves;
+        }
+
+        let (winner, combination) = match response {
+            DeclarationResponse::Good if elder_candidate.is_some() => (DeclarationWinner::Elder, elder_candidate),
+            DeclarationResponse::Good => (DeclarationWinner::Nobody, None),
+            DeclarationResponse::NotGood => (DeclarationWinner::Younger, younger_candidate),
+            DeclarationResponse::Equals => (DeclarationWinner::Tie, None),
+        };
+        self.set_declaration_winner(&ctype, winner, combination);
+        moves.append(&mut self.next_step());
+        moves
+    }
+
+    fn check_card(&self, player: PlayerId, card: &Card) -> Result<(), PiquetError> {
+        let hand = self.get_hand(player);
+        if !hand.contains(card) {
+            return Err(PiquetError::CardNotInHand);
+        }
+        if let Some(led) = &self.player(player.other()).cardPlayed {
+            if card.suit != led.suit && hand.iter().any(|c| c.suit == led.suit) {
+                return Err(PiquetError::MustFollowSuit);
+            }
+        }
+        Ok(())
+    }
+
+    fn play_card(&mut self, player: PlayerId, pmove: PlayerMove) -> Vec<Move> {
+        let card = match &pmove {
+            PlayerMove::PlayFirst(card) | PlayerMove::PlayCard(card) => card.clone(),
+            _ => panic!("Not a card")
+        };
+
+        let current = self.player_mut(player);
+        current.hand.remove(&card);
+        current.cardPlayed = Some(card);
+        let mut moves = vec![self.record(player, pmove)];
+
+        if self.step == Step::PlayFirstCard {
+            // the cards left by younger in the talon are now shown
+            let untaken = self.deck.take_n_cards(self.deck.len());
+            self.visible.add_cards(untaken);
+        }
+
+        if self.player(player.other()).cardPlayed.is_some() {
+            moves.append(&mut self.end_trick(player.other()));
+        } else {
+            self.isElderToPlay = !self.isElderToPlay;
+        }
+        if self.step == Step::PlayCards {
+            moves.append(&mut self.check_pique());
+        }
+
+        if self.step == Step::PlayFirstCard 
+            || (self.player1.hand.is_empty() && self.player2.hand.is_empty()) {
+            moves.append(&mut self.next_step());
+        }
+        moves
+    }
+
+    // The highest card of the suit led wins the trick, the winner leads the next one
+    fn end_trick(&mut self, leader: PlayerId) -> Vec<Move> {
+        let follower = leader.other();
+        let lead = self.player_mut(leader).cardPlayed.take().expect("No card led");
+        let follow = self.player_mut(follower).cardPlayed.take().expect("No card played");
+        let winner = if follow.suit == lead.suit && follow.rank > lead.rank { follower } else { leader };
+
+        let mut moves = vec![];
+        self.player_mut(winner).tricksWon += 1;
+        if winner == follower {
+            moves.push(self.record(winner, PlayerMove::WinAsSecond));
+        }
+        if self.player1.hand.is_empty() && self.player2.hand.is_empty() {
+            moves.push(self.record(winner, PlayerMove::WinLastTrick));
+        }
+        self.isElderToPlay = winner == self.elder();
+        moves
+    }
+
+    // Winning the majority of tricks scores ten, winning them all is a capot
+    fn set_cards_points(&mut self) -> Vec<Move> {
+        let p1_tricks = self.player1.tricksWon;
+        let p2_tricks = self.player2.tricksWon;
+        let (winner, loser_tricks) = match p1_tricks.cmp(&p2_tricks) {
+            Ordering::Greater => (PlayerId::P1, p2_tricks),
+            Ordering::Less => (PlayerId::P2, p1_tricks),
+            Ordering::Equal => return vec![]
+        };
+        if loser_tricks == 0 {
+            vec![self.record(winner, PlayerMove::Capot)]
+        } else {
+            vec![self.record(winner, PlayerMove::WinCards)]
+        }
+    }
+
+    pub fn get_tricks_won(&self, player: PlayerId) -> u32 {
+        self.player(player).tricksWon
+    }
+}
+
+// Games for the tests of this module and of the modules built on it
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+    use crate::agent::{Agent, RulesAgent};
+
+    // game of the seed, dealt : elder is to exchange
+    pub(crate) fn dealt_game(seed: u8) -> Game {
+        dealt_game_with_options(seed, GameOptions::default())
+    }
+
+    pub(crate) fn dealt_game_with_options(seed: u8, options: GameOptions) -> Game {
+        let mut game = Game::new_with_options([seed; 16], options);
+        game.choose_elder();
+        game.deal().unwrap();
+        game
+    }
+
+    // deal at elder's point declaration, elder having exchanged the first card, younger none
+    pub(crate) fn game_at_declaration(seed: u8) -> Game {
+        let mut game = dealt_game(seed);
+        let card = game.get_hand(game.elder()).iter().next().unwrap().clone();
+        game.play(game.elder(), PlayerMove::Exchange(Hand::new(vec![card]))).unwrap();
+        game.play(game.younger(), PlayerMove::Exchange(Hand::empty_hand())).unwrap();
+        game
+    }
+
+    // deal played by the rules of thumb up to the first card
+    pub(crate) fn game_at_first_card(seed: u8) -> Game {
+        let mut game = dealt_game(seed);
+        while game.get_step() != &Step::PlayFirstCard {
+            let player = game.player_to_play().unwrap();
+            let pmove = RulesAgent.choose_move(&game.view_for(player), &game.legal_moves(player));
+            game.play(player, pmove).unwrap();
+        }
+        assert_eq!(game.player_to_play(), Some(game.elder()));
+        assert_eq!(game.get_hand(game.younger()).len(), HAND_SIZE);
+        game
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::fixtures::dealt_game;
+
+    // plays the first card following suit
+    fn play_any_card(game: &mut Game, player: PlayerId) -> Vec<Move> {
+        let led = game.player(player.other()).cardPlayed.clone();
+        let hand = game.get_hand(player);
+        let card = led.and_then(|led| hand.iter().find(|c| c.suit == led.suit))
+            .or_else(|| hand.iter().next())
+            .unwrap()
+            .clone();
+        let pmove = if game.is_trick_start() { PlayerMove::PlayFirst(card) } else { PlayerMove::PlayCard(card) };
+        game.play(player, pmove).unwrap()
+    }
+
+    // declares the best combinations of each player
+    fn declare_all(game: &mut Game) {
+        let elder = game.elder();
+        let younger = game.younger();
+        for ctype in [CombinationType::Point, CombinationType::Sequence, CombinationType::Set].iter() {
+            let elder_best = get_best_combination(ctype, game.get_hand(elder));
+            let younger_best = get_best_combination(ctype, game.get_hand(younger));
+            let count = elder_best.as_ref().map_or(0, |c| c.len() as u32);
+            game.play(elder, PlayerMove::DeclarationCount(ctype.clone(), count)).unwrap();
+            let response = declaration_response(&elder_best, &younger_best, false);
+            game.play(younger, PlayerMove::PlayerResponse(ctype.clone(), response.clone())).unwrap();
+            if response == DeclarationResponse::Equals {
+                let upper = elder_best.clone().unwrap();
+                let pmove = match ctype {
+                    CombinationType::Point => PlayerMove::DeclarationCount(ctype.clone(), upper.get_cards().point_value()),
+                    _ => PlayerMove::DeclarationUpper(ctype.clone(), upper.max_rank().unwrap()),
+                };
+                game.play(elder, pmove).unwrap();
+                let response = declaration_response(&elder_best, &younger_best, true);
+                game.play(younger, PlayerMove::PlayerResponse(ctype.clone(), response)).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_deal() {
+        let game = dealt_game(1);
+        assert_eq!(game.get_step(), &Step::ExchangeElder);
+        assert_eq!(game.player1.hand.len(), 12);
+        assert_eq!(game.player2.hand.len(), 12);
+        assert_eq!(game.deck.len(), 8);
+    }
+
+    #[test]
+    fn test_play_checks() {
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        assert_eq!(
+            game.play(younger, PlayerMove::Exchange(Hand::empty_hand())),
+            Err(PiquetError::NotYourTurnError)
+        );
+        assert_eq!(
+            game.play(elder, PlayerMove::WinCards),
+            Err(PiquetError::InvalidForStepError(Step::ExchangeElder))
+        );
+        let card = game.get_hand(younger).iter().next().unwrap().clone();
+        assert_eq!(
+            game.play(elder, PlayerMove::Exchange(Hand::new(vec![card]))),
+            Err(PiquetError::CardNotInHand)
+        );
+
+        let card = game.get_hand(elder).iter().next().unwrap().clone();
+        let moves = game.play(elder, PlayerMove::Exchange(Hand::new(vec![card.clone()]))).unwrap();
+        assert_eq!(moves, vec![Move::new(elder, PlayerMove::Exchange(Hand::new(vec![card])))]);
+        assert_eq!(game.get_step(), &Step::ExchangeYounger);
+        assert_eq!(game.get_hand(elder).len(), 12);
+        assert_eq!(game.get_deal_moves().len(), 1);
+    }
+
+    fn play_deal(game: &mut Game) {
+        let elder = game.elder();
+        let younger = game.younger();
+        for &player in [elder, younger].iter() {
+            let card = game.get_hand(player).iter().next().unwrap().clone();
+            game.play(player, PlayerMove::Exchange(Hand::new(vec![card]))).unwrap();
+        }
+        declare_all(game);
+        assert_eq!(game.get_step(), &Step::PlayFirstCard);
+        while let Some(player) = game.player_to_play() {
+            play_any_card(game, player);
+        }
+    }
+
+    #[test]
+    fn test_play_deal() {
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        play_deal(&mut game);
+        assert_eq!(game.get_step(), &Step::End);
+        assert_eq!(game.get_tricks_won(elder) + game.get_tricks_won(younger), 12);
+        assert_eq!(game.get_deal_moves().iter().filter(|(m, _)| m.player_move().is_card()).count(), 24);
+    }
+
+    #[test]
+    fn test_exchange() {
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        let first_cards = |game: &Game, player, n| Hand::new(game.get_hand(player).iter().take(n).cloned().collect());
+
+        assert_eq!(
+            game.play(elder, PlayerMove::Exchange(Hand::empty_hand())),
+            Err(PiquetError::InvalidExchange)
+        );
+        assert_eq!(
+            game.play(elder, PlayerMove::Exchange(first_cards(&game, elder, 6))),
+            Err(PiquetError::InvalidExchange)
+        );
+        let card = game.get_hand(elder).iter().next().unwrap().clone();
+        assert_eq!(
+            game.play(elder, PlayerMove::Exchange(Hand::new(vec![card.clone(), card]))),
+            Err(PiquetError::CardNotInHand)
+        );
+
+        // elder leaves two cards and can look at them
+        let talon = game.get_talon().get_cards().clone();
+        let discard = first_cards(&game, elder, 3);
+        game.play(elder, PlayerMove::Exchange(discard.clone())).unwrap();
+        assert!(discard.iter().all(|c| !game.get_hand(elder).contains(c)));
+        assert!(talon[..3].iter().all(|c| game.get_hand(elder).contains(c)));
+        assert_eq!(game.player(elder).talonSeen, Hand::new(talon[3..5].to_vec()));
+        assert_eq!(game.exchange_limits(), (0, 5));
+
+        // younger takes four cards of the remaining five
+        assert_eq!(
+            game.play(younger, PlayerMove::Exchange(first_cards(&game, younger, 6))),
+            Err(PiquetError::InvalidExchange)
+        );
+        game.play(younger, PlayerMove::Exchange(first_cards(&game, younger, 4))).unwrap();
+        assert!(talon[3..7].iter().all(|c| game.get_hand(younger).contains(c)));
+        assert_eq!(game.player(younger).talonSeen, Hand::new(talon[7..].to_vec()));
+        assert!(game.get_visible().is_empty());
+
+        // the untaken card is shown once elder has led
+        declare_all(&mut game);
+        let card = game.get_hand(elder).iter().next().unwrap().clone();
+        game.play(elder, PlayerMove::PlayFirst(card)).unwrap();
+        assert_eq!(game.get_visible().get_cards(), &talon[7..].to_vec());
+    }
+
+    fn hand(cards: &str) -> Hand {
+        cards.parse().unwrap()
+    }
+
+    #[test]
+    fn test_declarations() {
+        use Rank::*;
+        use Suit::*;
+        use CombinationType::*;
+        use DeclarationResponse::*;
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        game.player_mut(elder).hand = hand("7h 8h Qh Kh Ah 7d 8d Ad 7s As 7c Ac");
+        game.player_mut(younger).hand = hand("8s 9s 10s Js Ks 9d 10d Kd 8c 9c 10c Kc");
+        game.step = Step::DeclarePointElder;
+
+        // point : five cards each, younger has the best total
+        assert_eq!(game.play(elder, PlayerMove::DeclarationCount(Point, 4)), Err(PiquetError::InvalidCombination));
+        game.play(elder, PlayerMove::DeclarationCount(Point, 5)).unwrap();
+        assert_eq!(game.play(younger, PlayerMove::PlayerResponse(Point, Good)), Err(PiquetError::InvalidCombination));
+        game.play(younger, PlayerMove::PlayerResponse(Point, Equals)).unwrap();
+        assert_eq!(game.get_step(), &Step::DeclarePointElder);
+        assert_eq!(game.play(elder, PlayerMove::DeclarationCount(Point, 47)), Err(PiquetError::InvalidCombination));
+        game.play(elder, PlayerMove::DeclarationCount(Point, 46)).unwrap();
+        game.play(younger, PlayerMove::PlayerResponse(Point, NotGood)).unwrap();
+        assert_eq!(game.get_declaration_winner(&Point), &DeclarationWinner::Younger);
+
+        // sequence : tierce against quart
+        game.play(elder, PlayerMove::DeclarationCount(Sequence, 3)).unwrap();
+        game.play(younger, PlayerMove::PlayerResponse(Sequence, NotGood)).unwrap();
+        assert_eq!(game.get_declaration_winner(&Sequence), &DeclarationWinner::Younger);
+
+        // set : quatorze of aces against two trios
+        game.play(elder, PlayerMove::DeclarationCount(Set, 4)).unwrap();
+        let moves = game.play(younger, PlayerMove::PlayerResponse(Set, Good)).unwrap();
+        assert_eq!(game.get_declaration_winner(&Set), &DeclarationWinner::Elder);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(game.player(elder).dealPoints, 14);
+        assert_eq!(game.get_step(), &Step::PlayFirstCard);
+
+        // younger scores the point and both sequences after elder's lead
+        let moves = game.play(elder, PlayerMove::PlayFirst(Card::new(Seven, Heart))).unwrap();
+        assert_eq!(moves.len(), 4);
+        assert_eq!(game.player(elder).dealPoints, 15);
+        assert_eq!(game.player(younger).dealPoints, 5 + 4 + 3);
+    }
+
+    #[test]
+    fn test_sink() {
+        use CombinationType::*;
+        use DeclarationResponse::*;
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        game.player_mut(elder).hand = hand("7h 8h Qh Kh Ah 7d 8d Ad 7s As 7c Ac");
+        game.player_mut(younger).hand = hand("8s 9s 10s Js Ks 9d 10d Kd 8c 9c 10c Kc");
+        game.step = Step::DeclarePointElder;
+
+        // only younger can sink, here conceding the point
+        assert_eq!(game.play(elder, PlayerMove::Sink(Point)), Err(PiquetError::InvalidForStepError(Step::DeclarePointElder)));
+        game.play(elder, PlayerMove::DeclarationCount(Point, 5)).unwrap();
+        game.play(younger, PlayerMove::PlayerResponse(Point, Equals)).unwrap();
+        game.play(elder, PlayerMove::DeclarationCount(Point, 46)).unwrap();
+        assert_eq!(game.play(younger, PlayerMove::Sink(Sequence)), Err(PiquetError::InvalidForStepError(Step::DeclarePointResponse)));
+        game.play(younger, PlayerMove::Sink(Point)).unwrap();
+        assert_eq!(game.get_declaration_winner(&Point), &DeclarationWinner::Elder);
+
+        // elder hides the tierce, younger's quart is good
+        game.play(elder, PlayerMove::DeclarationCount(Sequence, 0)).unwrap();
+        assert_eq!(game.play(younger, PlayerMove::PlayerResponse(Sequence, Good)), Err(PiquetError::InvalidCombination));
+        game.play(younger, PlayerMove::PlayerResponse(Sequence, NotGood)).unwrap();
+        assert_eq!(game.get_declaration_winner(&Sequence), &DeclarationWinner::Younger);
+
+        game.play(elder, PlayerMove::DeclarationCount(Set, 4)).unwrap();
+        game.play(younger, PlayerMove::Sink(Set)).unwrap();
+        assert_eq!(game.get_declaration_winner(&Set), &DeclarationWinner::Elder);
+        assert_eq!(game.player(elder).dealPoints, 5 + 14);
+    }
+
+    #[test]
+    fn test_tricks() {
+        use Rank::*;
+        use Suit::*;
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        game.player_mut(elder).hand = hand("7h As Ks");
+        game.player_mut(younger).hand = hand("Ah 8h 7c");
+        game.step = Step::PlayCards;
+
+        // younger must follow hearts and wins as second
+        game.play(elder, PlayerMove::PlayFirst(Card::new(Seven, Heart))).unwrap();
+        assert_eq!(game.play(younger, PlayerMove::PlayCard(Card::new(Seven, Club))), Err(PiquetError::MustFollowSuit));
+        let moves = game.play(younger, PlayerMove::PlayCard(Card::new(Ace, Heart))).unwrap();
+        assert_eq!(moves, vec![
+            Move::new(younger, PlayerMove::PlayCard(Card::new(Ace, Heart))),
+            Move::new(younger, PlayerMove::WinAsSecond),
+        ]);
+        assert_eq!(game.player_to_play(), Some(younger));
+
+        // elder can't follow and loses
+        game.play(younger, PlayerMove::PlayFirst(Card::new(Seven, Club))).unwrap();
+        assert_eq!(game.play(younger, PlayerMove::PlayFirst(Card::new(Eight, Heart))), Err(PiquetError::NotYourTurnError));
+        game.play(elder, PlayerMove::PlayCard(Card::new(King, Spade))).unwrap();
+        assert_eq!(game.player_to_play(), Some(younger));
+
+        // last trick
+        game.play(younger, PlayerMove::PlayFirst(Card::new(Eight, Heart))).unwrap();
+        let moves = game.play(elder, PlayerMove::PlayCard(Card::new(Ace, Spade))).unwrap();
+        assert_eq!(moves, vec![
+            Move::new(elder, PlayerMove::PlayCard(Card::new(Ace, Spade))),
+            Move::new(younger, PlayerMove::WinLastTrick),
+            Move::new(younger, PlayerMove::Capot),
+        ]);
+        assert_eq!(game.get_step(), &Step::End);
+        assert_eq!(game.player(elder).dealPoints, 1);
+        assert_eq!(game.player(younger).dealPoints, 1 + 1 + 1 + 1 + 40);
+    }
+
+    // elder holds a huitième in hearts and three or four kings, younger has no winning combination
+    fn set_bonus_hands(game: &mut Game, quatorze: bool) {
+        use Rank::*;
+        use Suit::*;
+        let elder = game.elder();
+        let younger = game.younger();
+        let mut elder_hand = hand("7h 8h 9h 10h Jh Qh Kh Ah Kd Ks 9d");
+        elder_hand.push(if quatorze { Card::new(King, Club) } else { Card::new(Nine, Spade) });
+        game.player_mut(elder).hand = elder_hand;
+        game.player_mut(younger).hand = hand("7d 8d 10d Qd 7s 8s 10s Qs 7c 8c 10c Qc");
+        game.step = Step::DeclarePointElder;
+    }
+
+    #[test]
+    fn test_repique() {
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        set_bonus_hands(&mut game, true);
+        declare_all(&mut game);
+        // point of eight, huitième, quatorze of kings
+        assert_eq!(game.player(elder).dealPoints, 8 + 18 + 14 + 60);
+        assert!(game.get_deal_moves().iter().any(|(m, _)| m == &Move::new(elder, PlayerMove::Repique)));
+        assert_eq!(game.player(younger).dealPoints, 0);
+
+        // no pique on top of the repique
+        let moves = play_any_card(&mut game, elder);
+        assert_eq!(moves.len(), 1);
+    }
+
+    #[test]
+    fn test_pique() {
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        set_bonus_hands(&mut game, false);
+        declare_all(&mut game);
+        // point of eight, huitième, trio of kings
+        assert_eq!(game.player(elder).dealPoints, 8 + 18 + 3);
+        assert!(!game.has_bonus(elder));
+
+        let moves = play_any_card(&mut game, elder);
+        assert_eq!(moves, vec![
+            Move::new(elder, PlayerMove::PlayFirst(Card::new(Rank::Seven, Suit::Heart))),
+            Move::new(elder, PlayerMove::Pique),
+        ]);
+        assert_eq!(game.player(elder).dealPoints, 30 + 30);
+    }
+
+    #[test]
+    fn test_carte_blanche() {
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        game.player_mut(elder).hand = hand("7h 8h 9h 10h Ah 7d 8d 9d 10d Ad 7s 8s");
+        game.player_mut(younger).hand = hand("9s 10s Js Qs Ks As 7c 8c 9c 10c Jc Qc");
+
+        assert_eq!(game.play(younger, PlayerMove::CarteBlanche), Err(PiquetError::InvalidCombination));
+        let moves = game.play(elder, PlayerMove::CarteBlanche).unwrap();
+        assert_eq!(moves, vec![Move::new(elder, PlayerMove::CarteBlanche)]);
+        assert_eq!(game.player(elder).dealPoints, 10);
+        assert_eq!(game.get_visible().len(), 12);
+        assert_eq!(game.get_step(), &Step::ExchangeElder);
+        assert_eq!(
+            game.play(elder, PlayerMove::CarteBlanche),
+            Err(PiquetError::InvalidForStepError(Step::ExchangeElder))
+        );
+
+        let mut game = fixtures::dealt_game_with_options(1, GameOptions { carteBlanche: false, carteRouge: false, competitive: false });
+        assert_eq!(
+            game.play(game.elder(), PlayerMove::CarteBlanche),
+            Err(PiquetError::InvalidForStepError(Step::ExchangeElder))
+        );
+    }
+
+    #[test]
+    fn test_carte_rouge() {
+        let mut game = fixtures::dealt_game_with_options(1, GameOptions { carteBlanche: true, carteRouge: true, competitive: false });
+        let elder = game.elder();
+        let younger = game.younger();
+        game.player_mut(elder).hand = hand("9h 10h Jh Qh Kh Ah Qd Kd Qs Ks Qc Kc");
+        game.player_mut(elder).leftUntilCarteRouge = game.get_hand(elder).clone();
+        game.player_mut(younger).hand = hand("7d 8d 9d 10d 7s 8s 9s 10s 7c 8c 9c 10c");
+        game.step = Step::DeclarePointElder;
+        declare_all(&mut game);
+        // point of six, sixième, quatorzes of kings and queens, repique and carte rouge
+        assert_eq!(game.player(elder).dealPoints, 6 + 16 + 14 + 14 + 60 + 20);
+        assert!(game.get_deal_moves().iter().any(|(m, _)| m == &Move::new(elder, PlayerMove::CarteRouge)));
+    }
+
+    #[test]
+    fn test_partie() {
+        let mut game = dealt_game(1);
+        let first_elder = game.elder();
+        assert_eq!(game.deal(), Err(PiquetError::InvalidForStepError(Step::ExchangeElder)));
+        let mut deal_points = (0, 0);
+        for deal in 0..6 {
+            assert_eq!(game.elder(), if deal % 2 == 0 { first_elder } else { first_elder.other() });
+            assert_eq!(game.get_partie_result(), None);
+            play_deal(&mut game);
+            deal_points.0 += game.get_deal_points(PlayerId::P1);
+            deal_points.1 += game.get_deal_points(PlayerId::P2);
+            if deal < 5 {
+                game.deal().unwrap();
+            }
+        }
+        assert_eq!(game.get_deal_num(), &Deal::Six);
+        assert_eq!(game.get_deals().len(), 6);
+        assert_eq!(game.deal(), Err(PiquetError::InvalidForStepError(Step::End)));
+        let result = game.get_partie_result().unwrap();
+        assert_eq!((result.player1Points, result.player2Points), deal_points);
+        assert_eq!(result, PartieResult::new(deal_points.0, deal_points.1));
+    }
+
+    #[test]
+    fn test_partie_result() {
+        let result = PartieResult::new(130, 110);
+        assert_eq!((result.winner, result.score, result.rubicon), (Some(PlayerId::P1), 120, false));
+        let result = PartieResult::new(80, 130);
+        assert_eq!((result.winner, result.score, result.rubicon), (Some(PlayerId::P2), 310, true));
+        let result = PartieResult::new(90, 70);
+        assert_eq!((result.winner, result.score, result.rubicon), (Some(PlayerId::P1), 160, true));
+        let result = PartieResult::new(100, 100);
+        assert_eq!((result.winner, result.score, result.rubicon), (None, 0, false));
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let mut game = dealt_game(1);
+        let elder = game.elder();
+        let younger = game.younger();
+        let exchanges = game.legal_moves(elder).into_iter()
+            .filter(|m| matches!(m, PlayerMove::Exchange(_)))
+            .count();
+        // 12 cards, 1 to 5 discarded
+        assert_eq!(exchanges, 12 + 66 + 220 + 495 + 792);
+        assert!(game.legal_moves(younger).iter().all(|m| m == &PlayerMove::CarteBlanche));
+
+        play_deal(&mut game);
+        assert!(game.legal_moves(elder).is_empty());
+        assert!(game.legal_moves(younger).is_empty());
+    }
+
+    // debug output of the game, without the moves to redo
+    fn snapshot(game: &mut Game) -> String {
+        let redo_moves = std::mem::take(&mut game.redoMoves);
+        let state = format!("{:?}", game);
+        game.redoMoves = redo_moves;
+        state
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut game = dealt_game(1);
+        play_deal(&mut game);
+        game.deal().unwrap();
+        let start = snapshot(&mut game);
+        assert_eq!(game.undo(), Err(PiquetError::NoSuchMove));
+
+        let elder = game.elder();
+        let younger = game.younger();
+        for &player in [elder, younger].iter() {
+            let card = game.get_hand(player).iter().next().unwrap().clone();
+            game.play(player, PlayerMove::Exchange(Hand::new(vec![card]))).unwrap();
+        }
+        let exchanged = snapshot(&mut game);
+        declare_all(&mut game);
+        play_any_card(&mut game, elder);
+        let played = snapshot(&mut game);
+        let move_index = game.get_move_index();
+
+        game.undo().unwrap();
+        assert_eq!(game.get_step(), &Step::PlayFirstCard);
+        assert_eq!(game.get_move_index(), move_index - 1);
+        while game.get_move_index() > 2 {
+            game.undo().unwrap();
+        }
+        assert_eq!(snapshot(&mut game), exchanged);
+        assert_eq!(game.get_declaration_winner(&CombinationType::Point), &DeclarationWinner::Nobody);
+        game.undo().unwrap();
+        game.undo().unwrap();
+        assert_eq!(snapshot(&mut game), start);
+
+        while game.redo().is_ok() {}
+        assert_eq!(snapshot(&mut game), played);
+        assert_eq!(snapshot(&mut game.state_at(2).unwrap()), exchanged);
+        assert!(game.state_at(move_index + 1).is_err());
+
+        // playing a new move forgets the undone ones
+        game.undo().unwrap();
+        play_any_card(&mut game, elder);
+        assert_eq!(game.redo(), Err(PiquetError::NoSuchMove));
+    }
+
+    #[test]
+    fn test_undo_competitive() {
+        let options = GameOptions { competitive: true, ..GameOptions::default() };
+        let mut game = fixtures::dealt_game_with_options(1, options);
+        let elder = game.elder();
+        let card = game.get_hand(elder).iter().next().unwrap().clone();
+        game.play(elder, PlayerMove::Exchange(Hand::new(vec![card]))).unwrap();
+        assert_eq!(game.undo(), Err(PiquetError::UndoDisabled));
+        assert_eq!(game.state_at(0).err(), Some(PiquetError::UndoDisabled));
+    }
+
+    #[test]
+    fn test_random_play() {
+        for seed in 0..4 {
+            let mut game = dealt_game(seed);
+            let mut rng = rand_xorshift::XorShiftRng::from_seed([seed + 1; 16]);
+            while !game.is_partie_over() {
+                if game.get_step() == &Step::End {
+                    game.deal().unwrap();
+                }
+                let player = game.player_to_play().unwrap();
+                let moves = game.legal_moves(player);
+                assert!(!moves.is_empty());
+                let pmove = moves[rng.gen_range(0, moves.len())].clone();
+                game.play(player, pmove).unwrap();
+            }
+            assert!(game.get_partie_result().is_some());
+        }
+    }
+}
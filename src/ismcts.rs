@@ -0,0 +1,321 @@
+// Information set Monte Carlo tree search : each iteration deals the hidden cards at random,
+// consistently with what the player has seen, and searches the tree of moves shared by all
+// these deals (single observer ISMCTS), until the end of the current deal
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use rand_core::SeedableRng;
+
+use crate::agent::{Agent, RulesAgent};
+use crate::cards::*;
+use crate::game::*;
+use crate::solver::Solver;
+use crate::strength::{announcements, draw_opponent_hand, draw_shown_discard};
+use crate::view::PlayerView;
+
+#[derive (Debug, Clone)]
+pub struct IsmctsConfig { pub iterations: usize
+                        // stops earlier when the time is up, the search is then not reproducible
+                        , pub time: Option<Duration>
+                        , pub exploration: f64
+                        , pub options: GameOptions
+                        // ends the rollouts in the card play with the double dummy solver
+                        , pub oracle: bool
+}
+
+impl Default for IsmctsConfig {
+    fn default() -> Self {
+        IsmctsConfig { iterations: 1000
+                     , time: None
+                     , exploration: 0.7
+                     , options: GameOptions::default()
+                     , oracle: false
+        }
+    }
+}
+
+struct Node { gmove: Option<Move>
+            , parent: Option<usize>
+            , children: Vec<usize>
+            , visits: f64
+            // sum of the rewards for the player of the move
+            , reward: f64
+            , availability: f64
+}
+
+impl Node {
+    fn new(gmove: Option<Move>, parent: Option<usize>) -> Self {
+        Node { gmove, parent, children: vec![], visits: 0.0, reward: 0.0, availability: 1.0 }
+    }
+}
+
+pub struct IsmctsAgent { config: IsmctsConfig
+                       , rng: rand_xorshift::XorShiftRng
+                       , solver: Solver
+}
+
+impl IsmctsAgent {
+    pub fn new(config: IsmctsConfig, seed: [u8; 16]) -> Self {
+        IsmctsAgent { config, rng: rand_xorshift::XorShiftRng::from_seed(seed), solver: Solver::new() }
+    }
+
+    // Deals the cards the player has not seen to the opponent and the talon, the opponent's hand
+    // holding the cards known to be theirs and matching the opponent's announcements. None when
+    // no such hand was found.
+    fn determinize(&mut self, view: &PlayerView) -> Option<Game> {
+        let opponent = view.player.other();
+        let known = view.known_cards();
+        let mut declared = CardSet::empty();
+        for gmove in view.dealMoves.iter().filter(|gmove| gmove.player() == opponent) {
+            if let PlayerMove::Declaration(comb) = gmove.player_move() {
+                declared = declared.union(CardSet::from(comb.get_cards()));
+            }
+        }
+        let shown_discard = draw_shown_discard(view, known.shown, &mut self.rng);
+        let fixed = known.opponent().union(declared.difference(known.out_of_play)).difference(shown_discard);
+        let unseen: Vec<Card> = CardSet::full().difference(known.all()).difference(fixed).iter().collect();
+        let size = view.opponentCardsCount.min(unseen.len() + fixed.len());
+        let opponent_hand = draw_opponent_hand(view, &announcements(view), fixed, &unseen, size, &mut self.rng)?;
+
+        // the cards of the talon seen and left are on top of it
+        let talon_seen = view.talon_seen_left();
+        let mut rest: Vec<Card> = unseen.into_iter().filter(|card| !opponent_hand.contains(card)).collect();
+        rest.shuffle(&mut self.rng);
+        let (hidden_talon, rest) = rest.split_at((view.talonCount - talon_seen.len()).min(rest.len()));
+        let mut deck = Deck::empty_deck();
+        deck.add_cards(talon_seen.iter().chain(hidden_talon.iter()).cloned().collect());
+        // an opponent who showed a carte blanche discarded some of the cards shown
+        let discard = if known.shown.is_empty() {
+            Hand::new(rest.iter().take(view.opponentDiscardCount.unwrap_or(0)).cloned().collect())
+        } else {
+            Hand::from(shown_discard)
+        };
+        Some(Game::from_view(view, self.config.options.clone(), opponent_hand, discard, deck, self.rng.gen()))
+    }
+
+    // Child of the node to explore among the moves available in this deal, or a new move to expand
+    fn select(&mut self, tree: &mut Vec<Node>, node: usize, legal: Vec<Move>) -> (usize, bool) {
+        let untried: Vec<&Move> = legal.iter()
+            .filter(|gmove| !tree[node].children.iter().any(|&child| tree[child].gmove.as_ref() == Some(gmove)))
+            .collect();
+        if !untried.is_empty() {
+            let gmove = untried[self.rng.gen_range(0, untried.len())].clone();
+            tree.push(Node::new(Some(gmove), Some(node)));
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            return (child, true);
+        }
+
+        let available: Vec<usize> = tree[node].children.iter().cloned()
+            .filter(|&child| legal.iter().any(|gmove| tree[child].gmove.as_ref() == Some(gmove)))
+            .collect();
+        for &child in available.iter() {
+            tree[child].availability += 1.0;
+        }
+        let exploration = self.config.exploration;
+        let ucb = |child: &Node| child.reward / child.visits + exploration * (child.availability.ln() / child.visits).sqrt();
+        let best = available.into_iter()
+            .max_by(|&a, &b| ucb(&tree[a]).partial_cmp(&ucb(&tree[b])).expect("NaN score"))
+            .expect("No available move");
+        (best, false)
+    }
+
+    // Nothing is learnt when no dealing matches the opponent's announcements
+    fn iterate(&mut self, tree: &mut Vec<Node>, view: &PlayerView) {
+        let mut game = match self.determinize(view) {
+            Some(game) => game,
+            None => return
+        };
+        let mut node = 0;
+        while let Some(player) = game.player_to_play() {
+            let legal: Vec<Move> = game.legal_moves(player).into_iter().map(|pmove| Move::new(player, pmove)).collect();
+            if legal.is_empty() {
+                break;
+            }
+            let (child, expanded) = self.select(tree, node, legal);
+            let gmove = tree[child].gmove.clone().expect("Root move");
+            game.play(gmove.player(), gmove.player_move().clone()).expect("Illegal move in search");
+            node = child;
+            if expanded {
+                break;
+            }
+        }
+
+        // rollout until the end of the deal
+        let me = view.player;
+        let mut points = None;
+        while let Some(player) = game.player_to_play() {
+            if self.config.oracle && matches!(game.get_step(), Step::PlayFirstCard | Step::PlayCards) {
+                let solution = self.solver.solve(&game).expect("Not in the card play");
+                points = Some(match me {
+                    PlayerId::P1 => (solution.player1_points, solution.player2_points),
+                    PlayerId::P2 => (solution.player2_points, solution.player1_points),
+                });
+                break;
+            }
+            let legal = game.legal_moves(player);
+            if legal.is_empty() {
+                break;
+            }
+            let pmove = RulesAgent.choose_move(&game.view_for(player), &legal);
+            game.play(player, pmove).expect("Illegal move in rollout");
+        }
+
+        let (mine, theirs) = points.unwrap_or_else(|| (game.get_deal_points(me), game.get_deal_points(me.other())));
+        let difference = mine as f64 - theirs as f64;
+        let reward = (difference / 30.0).tanh();
+        let mut current = Some(node);
+        while let Some(idx) = current {
+            let current_node = &mut tree[idx];
+            current_node.visits += 1.0;
+            if let Some(gmove) = &current_node.gmove {
+                current_node.reward += if gmove.player() == me { reward } else { -reward };
+            }
+            current = current_node.parent;
+        }
+    }
+}
+
+impl Agent for IsmctsAgent {
+    fn choose_move(&mut self, view: &PlayerView, legal_moves: &[PlayerMove]) -> PlayerMove {
+        if legal_moves.len() == 1 {
+            return legal_moves[0].clone();
+        }
+        let start = Instant::now();
+        let mut tree = vec![Node::new(None, None)];
+        for _ in 0..self.config.iterations {
+            if self.config.time.is_some_and(|time| start.elapsed() >= time) {
+                break;
+            }
+            self.iterate(&mut tree, view);
+        }
+
+        tree[0].children.iter()
+            .map(|&child| &tree[child])
+            .filter_map(|child| child.gmove.as_ref().map(|gmove| (child.visits, gmove.player_move())))
+            .filter(|(_, pmove)| legal_moves.contains(pmove))
+            .max_by(|a, b| a.0.partial_cmp(&b.0).expect("NaN visits"))
+            .map_or(&legal_moves[0], |(_, pmove)| pmove)
+            .clone()
+    }
+
+    fn set_time_limit(&mut self, time: Option<Duration>) {
+        self.config.time = time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::*;
+    use crate::game::fixtures::{dealt_game, game_at_first_card};
+
+    fn config(iterations: usize) -> IsmctsConfig {
+        IsmctsConfig { iterations, ..IsmctsConfig::default() }
+    }
+
+    #[test]
+    fn test_determinize() {
+        let game = game_at_first_card(1);
+        let younger = game.younger();
+        let view = game.view_for(younger);
+        let mut agent = IsmctsAgent::new(config(1), [2; 16]);
+        let sample = agent.determinize(&view).unwrap();
+        assert_eq!(sample.get_hand(younger), game.get_hand(younger));
+        assert_eq!(sample.get_hand(game.elder()).len(), 12);
+        assert_eq!(sample.get_step(), &Step::PlayFirstCard);
+        assert_eq!(sample.view_for(younger), view);
+        let all = CardSet::from(sample.get_hand(younger))
+            .union(CardSet::from(sample.get_hand(game.elder())));
+        assert_eq!(all.len(), 24);
+    }
+
+    #[test]
+    fn test_determinize_under_declared() {
+        use crate::combinations::*;
+        // elder declares a point shorter than the best one, and shorter than younger's
+        let (mut game, count) = (0..).find_map(|seed| {
+            let mut game = dealt_game(seed);
+            for player in [game.elder(), game.younger()] {
+                let card = game.get_hand(player).iter().next().unwrap().clone();
+                game.play(player, PlayerMove::Exchange(Hand::new(vec![card]))).unwrap();
+            }
+            let lengths: Vec<usize> = get_combinations(&CombinationType::Point, game.get_hand(game.elder()))
+                .iter().map(|comb| comb.len()).collect();
+            let (shortest, longest) = (*lengths.iter().min()?, *lengths.iter().max()?);
+            let younger = get_best_combination(&CombinationType::Point, game.get_hand(game.younger()))?.len();
+            if shortest < younger && younger <= longest { Some((game, shortest as u32)) } else { None }
+        }).unwrap();
+        let (elder, younger) = (game.elder(), game.younger());
+        game.play(elder, PlayerMove::DeclarationCount(CombinationType::Point, count)).unwrap();
+        let response = game.legal_moves(younger);
+        let view = game.view_for(younger);
+
+        let mut agent = IsmctsAgent::new(config(1), [3; 16]);
+        for _ in 0..20 {
+            let sample = agent.determinize(&view).unwrap();
+            assert_eq!(sample.view_for(younger), view);
+            // the same response is expected from younger
+            assert_eq!(sample.legal_moves(younger), response);
+        }
+    }
+
+    #[test]
+    fn test_determinize_known_cards() {
+        let mut game = dealt_game(11);
+        let (elder, younger) = (game.elder(), game.younger());
+        let shown = game.get_hand(elder).clone();
+        let discard = |game: &Game, player, count| Hand::new(game.get_hand(player).iter().take(count).cloned().collect());
+        game.play(elder, PlayerMove::Exchange(discard(&game, elder, 3))).unwrap();
+        let seen = game.view_for(elder).talonSeen;
+        game.play(younger, PlayerMove::Exchange(discard(&game, younger, 1))).unwrap();
+
+        // younger took the first card of the talon elder saw, the second one is left on top
+        let (taken, left) = (seen.iter().next().unwrap(), seen.iter().nth(1).unwrap());
+        let view = game.view_for(elder);
+        let mut agent = IsmctsAgent::new(config(1), [12; 16]);
+        for _ in 0..20 {
+            let sample = agent.determinize(&view).unwrap();
+            assert!(sample.get_hand(younger).contains(taken));
+            assert_eq!(&sample.get_talon().get_cards()[0], left);
+            assert_eq!(sample.view_for(elder), view);
+        }
+
+        // elder's hand shown as a carte blanche : younger deals elder the cards shown but three
+        let mut view = game.view_for(younger);
+        view.visible = Deck::empty_deck();
+        view.visible.add_cards(shown.clone().cards());
+        view.dealMoves.insert(0, Move::new(elder, PlayerMove::CarteBlanche));
+        for _ in 0..20 {
+            let sample = agent.determinize(&view).unwrap();
+            let hand = CardSet::from(sample.get_hand(elder));
+            assert_eq!(hand.intersection(CardSet::from(&shown)).len(), 9);
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let game = game_at_first_card(3);
+        let elder = game.elder();
+        let view = game.view_for(elder);
+        let legal = game.legal_moves(elder);
+        let first = IsmctsAgent::new(config(50), [4; 16]).choose_move(&view, &legal);
+        let second = IsmctsAgent::new(config(50), [4; 16]).choose_move(&view, &legal);
+        assert_eq!(first, second);
+        assert!(legal.contains(&first));
+    }
+
+    #[test]
+    fn test_play_partie() {
+        let mut game = Game::new([5; 16]);
+        let mut ismcts = IsmctsAgent::new(IsmctsConfig { time: Some(Duration::from_secs(1)), ..config(10) }, [6; 16]);
+        play_partie(&mut game, &mut ismcts, &mut RandomAgent::new([7; 16])).unwrap();
+        assert!(game.get_partie_result().is_some());
+
+        let game = game_at_first_card(8);
+        let mut oracle = IsmctsAgent::new(IsmctsConfig { oracle: true, ..config(3) }, [9; 16]);
+        let pmove = oracle.choose_move(&game.view_for(game.elder()), &game.legal_moves(game.elder()));
+        assert!(game.legal_moves(game.elder()).contains(&pmove));
+    }
+}
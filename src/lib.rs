@@ -1,10 +1,16 @@
-mod cards;
-mod combinations;
-mod game;
-
-pub fn run() {
-    println!("PIQUET");
-    let seed: [u8; 16] = rand::random();
-    let mut game = game::Game::new(seed.clone());
-    println!("Game for seed {:?}: {:?}", seed, game);
-}   
+pub mod cards;
+pub mod combinations;
+pub mod game;
+pub mod view;
+pub mod record;
+pub mod binary;
+pub mod aeson;
+pub mod strength;
+pub mod exchange;
+pub mod agent;
+pub mod ismcts;
+pub mod solver;
+pub mod arena;
+pub mod protocol;
+pub mod notation;
+pub mod api;
@@ -0,0 +1,464 @@
+// Text notation of a partie, one line per move so that records can be read and compared with
+// a diff tool :
+//
+//   [Player1 "Roméo"]
+//   [Player2 "Juliette"]
+//   [Date "2026-10-17"]
+//   [Variant "carte blanche"]
+//   [Seed "01010101010101010101010101010101"]
+//
+//   Deal 1
+//   Elder P2
+//   Hand P1 7♥ 9♥ J♦ ...
+//   Hand P2 8♥ 10♥ ...
+//   Talon A♣ 7♠ ...
+//   P2 exchange 7♥ 8♣ 9♠
+//   P1 exchange -
+//   P2 point 5
+//   P1 point equals
+//   P2 point 48
+//   P1 point good
+//   P2 sequence high K
+//   P1 sink set
+//   P2 scores point 7♥ 8♥ 9♥ 10♥ J♥ (5)
+//   Trick 1 P2 A♥ P1 7♥
+//   P1 scores cards (10)
+//   Score P1 23 P2 45, total P1 23 P2 45
+//
+// Only the headers and the moves sent by the players are read back : the hands, the talon and
+// the lines with the scores follow from the seed and the moves.
+//
+// In the values of the headers, the quotes, the backslashes and the line breaks are escaped with
+// a backslash as in `\"`, `\\`, `\n` and `\r`.
+use std::fmt;
+use std::str::FromStr;
+
+use crate::cards::*;
+use crate::combinations::CombinationType;
+use crate::game::*;
+use crate::record::GameRecord;
+
+#[derive (Debug, Clone, PartialEq, Eq)]
+pub struct Headers { pub player1: String
+                   , pub player2: String
+                   , pub date: String
+}
+
+#[derive (Debug, Clone, PartialEq, Eq)]
+pub enum NotationError { MissingHeader(&'static str)
+                       , InvalidSeed(String)
+                       , InvalidVariant(String)
+                       // line number, starting at 1
+                       , InvalidLine(usize)
+                       , InvalidCard(usize, ParseCardError)
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NotationError::MissingHeader(name) => write!(f, "missing header {}", name),
+            NotationError::InvalidSeed(seed) => write!(f, "invalid seed '{}', expected 32 hexadecimal digits", seed),
+            NotationError::InvalidVariant(variant) => write!(f, "invalid variant '{}'", variant),
+            NotationError::InvalidLine(line) => write!(f, "line {}: invalid line", line),
+            NotationError::InvalidCard(line, err) => write!(f, "line {}: {}", line, err),
+        }
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+// -----------  Writer -----------
+fn card_text(card: &Card) -> String {
+    format!("{}{}", card.rank, card.suit)
+}
+
+fn cards_text<'a>(cards: impl Iterator<Item = &'a Card>) -> String {
+    let cards: Vec<String> = cards.map(card_text).collect();
+    if cards.is_empty() { "-".to_string() } else { cards.join(" ") }
+}
+
+fn player_text(player: PlayerId) -> &'static str {
+    match player {
+        PlayerId::P1 => "P1",
+        PlayerId::P2 => "P2",
+    }
+}
+
+fn header_text(name: &str, value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    format!("[{} \"{}\"]", name, escaped)
+}
+
+pub(crate) fn seed_text(seed: &[u8; 16]) -> String {
+    seed.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn variant_text(options: &GameOptions) -> String {
+    let rules: Vec<&str> = [(options.carteBlanche, "carte blanche"), (options.carteRouge, "carte rouge"), (options.competitive, "competitive")]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect();
+    if rules.is_empty() { "plain".to_string() } else { rules.join(", ") }
+}
+
+fn ctype_text(ctype: &CombinationType) -> String {
+    ctype.to_string().to_lowercase()
+}
+
+// moves sent by the players, except the cards which are written by trick
+fn move_text(pmove: &PlayerMove) -> String {
+    use PlayerMove::*;
+    match pmove {
+        CarteBlanche => "carte blanche".to_string(),
+        Exchange(discard) => format!("exchange {}", cards_text(discard.iter())),
+        DeclarationCount(ctype, count) => format!("{} {}", ctype_text(ctype), count),
+        DeclarationUpper(ctype, rank) => format!("{} high {}", ctype_text(ctype), rank),
+        PlayerResponse(ctype, DeclarationResponse::Good) => format!("{} good", ctype_text(ctype)),
+        PlayerResponse(ctype, DeclarationResponse::NotGood) => format!("{} not good", ctype_text(ctype)),
+        PlayerResponse(ctype, DeclarationResponse::Equals) => format!("{} equals", ctype_text(ctype)),
+        Sink(ctype) => format!("sink {}", ctype_text(ctype)),
+        pmove => format!("{:?}", pmove),
+    }
+}
+
+// points scored by the engine, the ones for the cards led and the tricks won aside
+fn score_text(pmove: &PlayerMove, points: u32) -> Option<String> {
+    use PlayerMove::*;
+    let scored = match pmove {
+        Declaration(comb) => format!("{} {}", ctype_text(comb.get_type()), cards_text(comb.get_cards().iter())),
+        CarteRouge => "carte rouge".to_string(),
+        Repique => "repique".to_string(),
+        Pique => "pique".to_string(),
+        WinCards => "cards".to_string(),
+        Capot => "capot".to_string(),
+        _ => return None
+    };
+    Some(format!("scores {} ({})", scored, points))
+}
+
+// Writes the partie, replaying the record to show the hands and the scores. Fails with the
+// index of the first invalid move of the record.
+pub fn write_partie(record: &GameRecord, headers: &Headers) -> Result<String, (usize, PiquetError)> {
+    let game = record.replay()?;
+    let mut lines = vec![
+        header_text("Player1", &headers.player1),
+        header_text("Player2", &headers.player2),
+        header_text("Date", &headers.date),
+        header_text("Variant", &variant_text(&record.options)),
+        header_text("Seed", &seed_text(&record.seed)),
+    ];
+
+    let mut deals: Vec<(&Deal, &Vec<(Move, u32)>)> = game.get_deals().iter().map(|(deal, moves)| (deal, moves)).collect();
+    if game.get_step() != &Step::End {
+        deals.push((game.get_deal_num(), game.get_deal_moves()));
+    }
+    let mut first_move = 0;
+    let mut totals = [0, 0];
+    for (num, (_, moves)) in deals.into_iter().enumerate() {
+        let mut start = record.replay_until(first_move)?;
+        if start.get_step() == &Step::End {
+            start.deal().map_err(|e| (first_move, e))?;
+        }
+        lines.push(String::new());
+        lines.push(format!("Deal {}", num + 1));
+        lines.push(format!("Elder {}", player_text(start.elder())));
+        for &player in [PlayerId::P1, PlayerId::P2].iter() {
+            let mut hand = start.get_hand(player).clone();
+            hand.sort_by_suit();
+            lines.push(format!("Hand {} {}", player_text(player), cards_text(hand.iter())));
+        }
+        lines.push(format!("Talon {}", cards_text(start.get_talon().get_cards().iter())));
+
+        let mut trick: Vec<String> = vec![];
+        let mut trick_count = 0;
+        // scores coming while a trick is played are written after it
+        let mut pending = vec![];
+        let mut points = [0, 0];
+        for (gmove, move_points) in moves.iter() {
+            let player = gmove.player();
+            points[player as usize] += move_points;
+            let pmove = gmove.player_move();
+            if let PlayerMove::PlayFirst(card) | PlayerMove::PlayCard(card) = pmove {
+                trick.push(format!("{} {}", player_text(player), card_text(card)));
+                if trick.len() == 2 {
+                    trick_count += 1;
+                    lines.push(format!("Trick {} {}", trick_count, trick.join(" ")));
+                    lines.append(&mut pending);
+                    trick.clear();
+                }
+            } else if pmove.is_automatic() {
+                if let Some(text) = score_text(pmove, *move_points) {
+                    let line = format!("{} {}", player_text(player), text);
+                    if trick.is_empty() { lines.push(line) } else { pending.push(line) }
+                }
+            } else {
+                lines.push(format!("{} {}", player_text(player), move_text(pmove)));
+            }
+            if !pmove.is_automatic() {
+                first_move += 1;
+            }
+        }
+        if !trick.is_empty() {
+            lines.push(format!("Trick {} {}", trick_count + 1, trick.join(" ")));
+            lines.append(&mut pending);
+        }
+        totals[0] += points[0];
+        totals[1] += points[1];
+        lines.push(format!("Score P1 {} P2 {}, total P1 {} P2 {}", points[0], points[1], totals[0], totals[1]));
+    }
+    lines.push(String::new());
+    Ok(lines.join("\n"))
+}
+
+// -----------  Parser -----------
+fn parse_player(s: &str) -> Option<PlayerId> {
+    match s {
+        "P1" => Some(PlayerId::P1),
+        "P2" => Some(PlayerId::P2),
+        _ => None
+    }
+}
+
+fn parse_ctype(s: &str) -> Option<CombinationType> {
+    match s {
+        "point" => Some(CombinationType::Point),
+        "sequence" => Some(CombinationType::Sequence),
+        "set" => Some(CombinationType::Set),
+        _ => None
+    }
+}
+
+pub(crate) fn parse_seed(s: &str) -> Result<[u8; 16], NotationError> {
+    let invalid = || NotationError::InvalidSeed(s.to_string());
+    if s.len() != 32 || !s.is_ascii() {
+        return Err(invalid());
+    }
+    let mut seed = [0; 16];
+    for (idx, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * idx..2 * idx + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(seed)
+}
+
+fn parse_variant(s: &str) -> Result<GameOptions, NotationError> {
+    let mut options = GameOptions { carteBlanche: false, carteRouge: false, competitive: false };
+    if s.trim() == "plain" {
+        return Ok(options);
+    }
+    for rule in s.split(',') {
+        match rule.trim() {
+            "carte blanche" => options.carteBlanche = true,
+            "carte rouge" => options.carteRouge = true,
+            "competitive" => options.competitive = true,
+            _ => return Err(NotationError::InvalidVariant(s.to_string())),
+        }
+    }
+    Ok(options)
+}
+
+// value of a header written by `header_text`, None if a quote is not escaped or an escape is
+// unknown
+fn parse_header_value(s: &str) -> Option<String> {
+    let mut value = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return None,
+            '\\' => value.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                'r' => '\r',
+                _ => return None,
+            }),
+            c => value.push(c),
+        }
+    }
+    Some(value)
+}
+
+// the move after the player, None if the line is not a move
+fn parse_move(text: &str, line: usize) -> Result<Option<PlayerMove>, NotationError> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let invalid = NotationError::InvalidLine(line);
+    let pmove = match words.as_slice() {
+        ["scores", ..] => return Ok(None),
+        ["carte", "blanche"] => PlayerMove::CarteBlanche,
+        ["exchange", "-"] => PlayerMove::Exchange(Hand::empty_hand()),
+        ["exchange", ..] => {
+            let discard = Hand::from_str(&words[1..].join(" ")).map_err(|err| NotationError::InvalidCard(line, err))?;
+            PlayerMove::Exchange(discard)
+        },
+        ["sink", ctype] => PlayerMove::Sink(parse_ctype(ctype).ok_or(invalid)?),
+        [ctype, rest @ ..] => {
+            let ctype = parse_ctype(ctype).ok_or(invalid.clone())?;
+            match rest {
+                ["good"] => PlayerMove::PlayerResponse(ctype, DeclarationResponse::Good),
+                ["not", "good"] => PlayerMove::PlayerResponse(ctype, DeclarationResponse::NotGood),
+                ["equals"] => PlayerMove::PlayerResponse(ctype, DeclarationResponse::Equals),
+                ["high", rank] => PlayerMove::DeclarationUpper(ctype, rank.parse().map_err(|err| NotationError::InvalidCard(line, err))?),
+                [count] => PlayerMove::DeclarationCount(ctype, count.parse().map_err(|_| invalid)?),
+                _ => return Err(invalid)
+            }
+        },
+        _ => return Err(invalid)
+    };
+    Ok(Some(pmove))
+}
+
+// `Trick n P2 A♥ P1 7♥`, the second card being missing while the trick is played
+fn parse_trick(words: &[&str], line: usize) -> Result<Vec<Move>, NotationError> {
+    let invalid = || NotationError::InvalidLine(line);
+    if words.len() != 4 && words.len() != 6 || words[1].parse::<usize>().is_err() {
+        return Err(invalid());
+    }
+    words[2..].chunks(2).enumerate().map(|(idx, played)| {
+        let player = parse_player(played[0]).ok_or_else(invalid)?;
+        let card = Card::from_str(played[1]).map_err(|err| NotationError::InvalidCard(line, err))?;
+        let pmove = if idx == 0 { PlayerMove::PlayFirst(card) } else { PlayerMove::PlayCard(card) };
+        Ok(Move::new(player, pmove))
+    }).collect()
+}
+
+// Reads the headers and the moves of a partie written by `write_partie`
+pub fn parse_partie(text: &str) -> Result<(Headers, GameRecord), NotationError> {
+    let (mut player1, mut player2, mut date, mut variant, mut seed) = (None, None, None, None, None);
+    let mut moves = vec![];
+    for (idx, raw) in text.lines().enumerate() {
+        let line = idx + 1;
+        let raw = raw.trim();
+        if raw.is_empty() || raw.starts_with(';') {
+            continue;
+        }
+        if raw.starts_with('[') {
+            let header = raw.strip_prefix('[').and_then(|h| h.strip_suffix(']')).ok_or(NotationError::InvalidLine(line))?;
+            let (name, value) = header.split_once(' ').ok_or(NotationError::InvalidLine(line))?;
+            let value = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+                .and_then(parse_header_value)
+                .ok_or(NotationError::InvalidLine(line))?;
+            match name {
+                "Player1" => player1 = Some(value),
+                "Player2" => player2 = Some(value),
+                "Date" => date = Some(value),
+                "Variant" => variant = Some(parse_variant(&value)?),
+                "Seed" => seed = Some(parse_seed(&value)?),
+                // unknown headers are kept for other tools
+                _ => ()
+            }
+            continue;
+        }
+
+        let words: Vec<&str> = raw.split_whitespace().collect();
+        match words[0] {
+            "Deal" | "Elder" | "Hand" | "Talon" | "Score" => (),
+            "Trick" => moves.append(&mut parse_trick(&words, line)?),
+            word => {
+                let player = parse_player(word).ok_or(NotationError::InvalidLine(line))?;
+                let text = raw[word.len()..].trim();
+                if let Some(pmove) = parse_move(text, line)? {
+                    moves.push(Move::new(player, pmove));
+                }
+            }
+        }
+    }
+
+    let headers = Headers { player1: player1.ok_or(NotationError::MissingHeader("Player1"))?
+                          , player2: player2.ok_or(NotationError::MissingHeader("Player2"))?
+                          , date: date.ok_or(NotationError::MissingHeader("Date"))?
+    };
+    let record = GameRecord { seed: seed.ok_or(NotationError::MissingHeader("Seed"))?
+                            , options: variant.ok_or(NotationError::MissingHeader("Variant"))?
+                            , moves
+    };
+    Ok((headers, record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::*;
+
+    fn headers() -> Headers {
+        Headers { player1: "Roméo".to_string(), player2: "Juliette".to_string(), date: "2026-10-17".to_string() }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut game = Game::new_with_options([3; 16], GameOptions { carteBlanche: true, carteRouge: true, competitive: false });
+        play_partie(&mut game, &mut RulesAgent, &mut RandomAgent::new([4; 16])).unwrap();
+        let record = game.get_record();
+        let text = write_partie(&record, &headers()).unwrap();
+        assert!(text.starts_with("[Player1 \"Roméo\"]\n[Player2 \"Juliette\"]\n[Date \"2026-10-17\"]\n[Variant \"carte blanche, carte rouge\"]\n[Seed \"03030303030303030303030303030303\"]\n\nDeal 1\n"));
+        assert_eq!(text.matches("\nTrick 12 ").count(), 6);
+        assert_eq!(parse_partie(&text), Ok((headers(), record)));
+
+        // partie in progress, in the middle of a trick
+        let mut record = game.get_record();
+        let first_card = record.moves.iter().position(|gmove| gmove.player_move().is_card()).unwrap();
+        record.moves.truncate(first_card + 1);
+        let text = write_partie(&record, &headers()).unwrap();
+        assert!(text.contains("\nTrick 1 P"));
+        assert_eq!(parse_partie(&text).unwrap().1, record);
+    }
+
+    #[test]
+    fn test_header_escapes() {
+        let mut game = Game::new([5; 16]);
+        play_partie(&mut game, &mut RulesAgent, &mut RulesAgent).unwrap();
+        let record = game.get_record();
+        let headers = Headers { player1: "Roméo \"le fou\"".to_string()
+                              , player2: "C:\\Juliette\\\"".to_string()
+                              , date: "2026-10-17\n[Seed \"00\"]\r".to_string()
+        };
+        let text = write_partie(&record, &headers).unwrap();
+        assert!(text.starts_with("[Player1 \"Roméo \\\"le fou\\\"\"]\n[Player2 \"C:\\\\Juliette\\\\\\\"\"]\n[Date \"2026-10-17\\n[Seed \\\"00\\\"]\\r\"]\n"));
+        assert_eq!(parse_partie(&text), Ok((headers, record)));
+
+        // a quote not escaped, an escaped closing quote, an unknown escape
+        for header in ["[Player1 \"a\"b\"]", "[Player1 \"a\\\"]", "[Date \"\\t\"]"].iter() {
+            assert_eq!(parse_partie(header), Err(NotationError::InvalidLine(1)));
+        }
+    }
+
+    #[test]
+    fn test_moves() {
+        let moves = [ PlayerMove::CarteBlanche
+                    , PlayerMove::Exchange(Hand::empty_hand())
+                    , PlayerMove::Exchange("10h As".parse().unwrap())
+                    , PlayerMove::DeclarationCount(CombinationType::Point, 48)
+                    , PlayerMove::DeclarationUpper(CombinationType::Sequence, Rank::Ten)
+                    , PlayerMove::PlayerResponse(CombinationType::Set, DeclarationResponse::NotGood)
+                    , PlayerMove::PlayerResponse(CombinationType::Point, DeclarationResponse::Equals)
+                    , PlayerMove::Sink(CombinationType::Set)
+        ];
+        for pmove in moves.iter() {
+            assert_eq!(parse_move(&move_text(pmove), 1), Ok(Some(pmove.clone())));
+        }
+        assert_eq!(move_text(&moves[2]), "exchange 10♥ A♠");
+        assert_eq!(parse_move("scores capot (40)", 1), Ok(None));
+        assert_eq!(parse_move("point high", 3), Err(NotationError::InvalidLine(3)));
+        assert_eq!(parse_move("exchange 1h", 4), Err(NotationError::InvalidCard(4, ParseCardError::UnknownRank("1".to_string()))));
+    }
+
+    #[test]
+    fn test_errors() {
+        let text = "[Player1 \"a\"]\n[Player2 \"b\"]\n[Date \"\"]\n[Variant \"plain\"]\n";
+        assert_eq!(parse_partie(text), Err(NotationError::MissingHeader("Seed")));
+        let with_seed = format!("{}[Seed \"{}\"]\n", text, "ab".repeat(16));
+        let (_, record) = parse_partie(&with_seed).unwrap();
+        assert_eq!((record.seed, record.options.carteBlanche), ([0xab; 16], false));
+        assert_eq!(parse_partie(&format!("{}[Seed \"ab\"]", text)), Err(NotationError::InvalidSeed("ab".to_string())));
+        assert_eq!(parse_partie(&format!("{}Trick 1 P1", with_seed)), Err(NotationError::InvalidLine(6)));
+        assert_eq!(parse_partie(&format!("{}P3 sink set", with_seed)), Err(NotationError::InvalidLine(6)));
+    }
+}
@@ -0,0 +1,288 @@
+// Line protocol between the arena and an engine written in any language, in the manner of UCI.
+// Game values are sent as JSON on a single line, with the serde encoding of the game types.
+//
+//   arena -> engine                      engine -> arena
+//   piquet                               id name <name>  (optional)
+//                                        piquetok
+//   isready                              readyok
+//   position <PlayerView>
+//   moves <[PlayerMove]>                 legal moves for the position
+//   go [movetime <milliseconds>]         bestmove <PlayerMove>
+//   quit
+//
+// Both sides ignore the lines they don't know. An engine may send `info <text>` lines at any time.
+// The arena waits for `readyok` before each position, so that an answer sent too late is not
+// taken for the next one.
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::agent::Agent;
+use crate::game::PlayerMove;
+use crate::view::PlayerView;
+
+// time given to the engine on top of the move time, for its answers to reach us
+const GRACE_TIME: Duration = Duration::from_secs(1);
+const HANDSHAKE_TIME: Duration = Duration::from_secs(10);
+// time given to choose a move when there is no time limit, so that a stuck engine can't block
+const MAX_MOVE_TIME: Duration = Duration::from_secs(600);
+
+#[derive (Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError { Io(String)
+                       , Timeout
+                       , Closed
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolError::Io(message) => write!(f, "engine input/output error: {}", message),
+            ProtocolError::Timeout => write!(f, "engine did not answer in time"),
+            ProtocolError::Closed => write!(f, "engine closed its output"),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<std::io::Error> for ProtocolError {
+    fn from(err: std::io::Error) -> Self {
+        ProtocolError::Io(err.to_string())
+    }
+}
+
+// -----------  Engine side -----------
+// Answers the commands read from `input` with the moves chosen by the agent, until `quit` or
+// the end of the input
+pub fn serve<R: BufRead, W: Write>(name: &str, agent: &mut dyn Agent, input: R, mut output: W) -> Result<(), ProtocolError> {
+    let mut view: Option<PlayerView> = None;
+    let mut legal_moves: Vec<PlayerMove> = vec![];
+    for line in input.lines() {
+        let line = line?;
+        let (command, args) = match line.trim().find(' ') {
+            Some(idx) => (&line.trim()[..idx], line.trim()[idx + 1..].trim()),
+            None => (line.trim(), ""),
+        };
+        match command {
+            "piquet" => {
+                writeln!(output, "id name {}", name)?;
+                writeln!(output, "piquetok")?;
+            },
+            "isready" => writeln!(output, "readyok")?,
+            "position" => match serde_json::from_str(args) {
+                Ok(position) => view = Some(position),
+                Err(err) => writeln!(output, "info error invalid position: {}", err)?,
+            },
+            "moves" => match serde_json::from_str(args) {
+                Ok(moves) => legal_moves = moves,
+                Err(err) => writeln!(output, "info error invalid moves: {}", err)?,
+            },
+            "go" => {
+                let movetime = args.strip_prefix("movetime")
+                    .and_then(|ms| ms.trim().parse::<u64>().ok())
+                    .map(Duration::from_millis);
+                match &view {
+                    Some(position) if !legal_moves.is_empty() => {
+                        agent.set_time_limit(movetime);
+                        let pmove = agent.choose_move(position, &legal_moves);
+                        let encoded = serde_json::to_string(&pmove).expect("Move encoding failed");
+                        writeln!(output, "bestmove {}", encoded)?;
+                    },
+                    _ => writeln!(output, "info error no position or legal moves")?,
+                }
+            },
+            "quit" => break,
+            _ => ()
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+// -----------  Arena side -----------
+// An engine run as a subprocess, playing through the protocol
+pub struct EngineAgent { name: String
+                       , child: Child
+                       , stdin: ChildStdin
+                       , lines: Receiver<String>
+                       , movetime: Option<Duration>
+                       , failures: usize
+}
+
+impl EngineAgent {
+    // Starts the engine and waits for its handshake. The engine is given `movetime` to choose
+    // each move, or all the time it needs.
+    pub fn spawn(program: &str, args: &[&str], movetime: Option<Duration>) -> Result<Self, ProtocolError> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("No engine input");
+        let stdout = child.stdout.take().expect("No engine output");
+        // reads in its own thread so that waiting for an answer can time out
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if sender.send(line).is_err() { break },
+                    Err(_) => break,
+                }
+            }
+        });
+
+        let mut engine = EngineAgent { name: program.to_string(), child, stdin, lines, movetime, failures: 0 };
+        engine.send("piquet")?;
+        let deadline = Instant::now() + HANDSHAKE_TIME;
+        loop {
+            let line = engine.receive(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if line.trim() == "piquetok" {
+                return Ok(engine);
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), ProtocolError> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    // Next line of the engine, if it comes before the deadline
+    fn receive(&self, deadline: Instant) -> Result<String, ProtocolError> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        self.lines.recv_timeout(timeout).map_err(|err| match err {
+            RecvTimeoutError::Timeout => ProtocolError::Timeout,
+            RecvTimeoutError::Disconnected => ProtocolError::Closed,
+        })
+    }
+
+    // Sends the position and waits for the move of the engine, whether legal or not. The
+    // answers to a previous request which came too late are skipped by waiting for the engine
+    // to be ready first.
+    pub fn request_move(&mut self, view: &PlayerView, legal_moves: &[PlayerMove]) -> Result<String, ProtocolError> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIME;
+        while self.receive(deadline)?.trim() != "readyok" {}
+
+        let position = serde_json::to_string(view).expect("View encoding failed");
+        let moves = serde_json::to_string(legal_moves).expect("Moves encoding failed");
+        self.send(&format!("position {}", position))?;
+        self.send(&format!("moves {}", moves))?;
+        match self.movetime {
+            Some(movetime) => self.send(&format!("go movetime {}", movetime.as_millis()))?,
+            None => self.send("go")?,
+        }
+        let deadline = Instant::now() + self.movetime.unwrap_or(MAX_MOVE_TIME) + GRACE_TIME;
+        loop {
+            let line = self.receive(deadline)?;
+            if let Some(pmove) = line.strip_prefix("bestmove ") {
+                return Ok(pmove.trim().to_string());
+            }
+        }
+    }
+}
+
+impl Agent for EngineAgent {
+    // A failing engine plays the first legal move, the failure being counted
+    fn choose_move(&mut self, view: &PlayerView, legal_moves: &[PlayerMove]) -> PlayerMove {
+        let pmove = self.request_move(view, legal_moves).ok()
+            .and_then(|answer| serde_json::from_str::<PlayerMove>(&answer).ok())
+            .filter(|pmove| legal_moves.contains(pmove));
+        pmove.unwrap_or_else(|| {
+            self.failures += 1;
+            legal_moves[0].clone()
+        })
+    }
+
+    fn set_time_limit(&mut self, time: Option<Duration>) {
+        self.movetime = time;
+    }
+
+    fn failures(&self) -> usize {
+        self.failures
+    }
+}
+
+impl Drop for EngineAgent {
+    fn drop(&mut self) {
+        if self.send("quit").is_err() || self.receive(Instant::now() + GRACE_TIME) != Err(ProtocolError::Closed) {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::RulesAgent;
+    use crate::combinations::CombinationType;
+    use crate::game::*;
+    use crate::game::fixtures::game_at_declaration;
+
+    #[test]
+    fn test_serve() {
+        let game = game_at_declaration(1);
+        let elder = game.elder();
+        let legal = game.legal_moves(elder);
+        let input = format!("piquet\nisready\ngo\nposition {}\nmoves {}\nunknown\ngo movetime 10\nquit\nisready\n",
+                            serde_json::to_string(&game.view_for(elder)).unwrap(),
+                            serde_json::to_string(&legal).unwrap());
+        let mut output = vec![];
+        serve("rules", &mut RulesAgent, input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[..3], ["id name rules", "piquetok", "readyok"]);
+        assert!(lines[3].starts_with("info error"));
+        let pmove: PlayerMove = serde_json::from_str(lines[4].strip_prefix("bestmove ").unwrap()).unwrap();
+        assert!(legal.contains(&pmove));
+        // nothing after quit
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_engine_agent() {
+        let game = game_at_declaration(1);
+        let elder = game.elder();
+        let legal = game.legal_moves(elder);
+        let script = |answer: &str| format!("while read line; do case \"$line\" in piquet) echo 'id name script'; echo piquetok;; isready) echo readyok;; go*) {};; quit) exit;; esac; done", answer);
+        let bestmove = |pmove: &PlayerMove| format!("echo 'bestmove {}'", serde_json::to_string(pmove).unwrap());
+
+        let mut engine = EngineAgent::spawn("sh", &["-c", &script(&bestmove(&PlayerMove::DeclarationCount(CombinationType::Point, 0)))], None).unwrap();
+        assert_eq!(engine.name(), "script");
+        let sink = PlayerMove::DeclarationCount(CombinationType::Point, 0);
+        assert_eq!(engine.choose_move(&game.view_for(elder), &legal), sink);
+        assert_eq!(engine.failures(), 0);
+
+        // illegal move
+        let mut engine = EngineAgent::spawn("sh", &["-c", &script(&bestmove(&PlayerMove::Sink(CombinationType::Set)))], Some(Duration::from_millis(10))).unwrap();
+        assert_eq!(engine.choose_move(&game.view_for(elder), &legal[1..]), legal[1]);
+        assert_eq!(engine.failures(), 1);
+
+        // no answer
+        let mut engine = EngineAgent::spawn("sh", &["-c", &script("")], None).unwrap();
+        engine.set_time_limit(Some(Duration::from_millis(10)));
+        assert_eq!(engine.request_move(&game.view_for(elder), &legal), Err(ProtocolError::Timeout));
+        assert_eq!(engine.choose_move(&game.view_for(elder), &legal), legal[0]);
+        assert_eq!(engine.failures(), 1);
+
+        // the first answer comes too late, the second one in time
+        let late = format!("if [ -z \"$answered\" ]; then answered=1; sleep 2; {}; else {}; fi", bestmove(&legal[1]), bestmove(&legal[2]));
+        let mut engine = EngineAgent::spawn("sh", &["-c", &script(&late)], Some(Duration::from_millis(10))).unwrap();
+        assert_eq!(engine.choose_move(&game.view_for(elder), &legal), legal[0]);
+        assert_eq!(engine.choose_move(&game.view_for(elder), &legal), legal[2]);
+        assert_eq!(engine.failures(), 1);
+        assert!(EngineAgent::spawn("sh", &["-c", "exit"], None).is_err());
+    }
+}
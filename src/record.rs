@@ -0,0 +1,86 @@
+use serde::{Serialize, Deserialize};
+
+use crate::game::*;
+
+// Everything needed to rebuild a game : the seed of the shuffles and the moves sent by the 
+// players. The game is expected to start with choose_elder then deal, as in lib::run.
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameRecord { pub seed: [u8; 16]
+                      , pub options: GameOptions
+                      , pub moves: Vec<Move>
+}
+
+impl GameRecord {
+    pub fn new(game: &Game) -> Self {
+        GameRecord { seed: *game.get_seed()
+                   , options: game.get_options().clone()
+                   , moves: game.get_player_moves()
+        }
+    }
+
+    // Plays back all the moves, returns the index of the first invalid move and its error
+    pub fn replay(&self) -> Result<Game, (usize, PiquetError)> {
+        self.replay_until(self.moves.len())
+    }
+
+    // Game state after the first `count` moves of the record
+    pub fn replay_until(&self, count: usize) -> Result<Game, (usize, PiquetError)> {
+        let mut game = Game::new_with_options(self.seed, self.options.clone());
+        game.choose_elder();
+        game.deal().map_err(|e| (0, e))?;
+        for (idx, gmove) in self.moves.iter().take(count).enumerate() {
+            if game.get_step() == &Step::End {
+                game.deal().map_err(|e| (idx, e))?;
+            }
+            game.play(gmove.player(), gmove.player_move().clone()).map_err(|e| (idx, e))?;
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use rand_core::SeedableRng;
+    use crate::game::fixtures::dealt_game;
+
+    fn random_game(seed: u8, max_moves: usize) -> Game {
+        let mut game = dealt_game(seed);
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([seed + 1; 16]);
+        for _ in 0..max_moves {
+            if game.is_partie_over() {
+                break;
+            }
+            if game.get_step() == &Step::End {
+                game.deal().unwrap();
+            }
+            let player = game.player_to_play().unwrap();
+            let moves = game.legal_moves(player);
+            let pmove = moves[rng.gen_range(0, moves.len())].clone();
+            game.play(player, pmove).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_replay() {
+        for &max_moves in [0, 30, 1000].iter() {
+            let game = random_game(5, max_moves);
+            let record = game.get_record();
+            let json = serde_json::to_string(&record).unwrap();
+            let record: GameRecord = serde_json::from_str(&json).unwrap();
+            let replayed = record.replay().unwrap();
+            assert_eq!(format!("{:?}", replayed), format!("{:?}", game));
+        }
+    }
+
+    #[test]
+    fn test_replay_invalid_move() {
+        let game = random_game(6, 10);
+        let mut record = game.get_record();
+        let gmove = record.moves[3].clone();
+        record.moves.insert(4, gmove);
+        assert_eq!(record.replay().unwrap_err().0, 4);
+    }
+}
@@ -0,0 +1,363 @@
+// Double dummy solver : once both hands are known, the card play is a game of perfect
+// information, solved by alpha-beta search with a transposition table over the cards left
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+
+use crate::cards::*;
+use crate::game::*;
+
+// above any difference of points in the card play
+const INFINITY: i32 = 1000;
+// positions kept in the transposition table, which is emptied when full
+const TABLE_SIZE: usize = 1 << 20;
+
+// Points of the whole deal for each player when both play the best cards until its end
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Solution { pub player1_tricks: u32
+                    , pub player2_tricks: u32
+                    , pub player1_points: u32
+                    , pub player2_points: u32
+                    // cards played from the position, best first
+                    , pub line: Vec<Move>
+}
+
+// Card play state, from the point of view of the elder
+#[derive (Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Position { elder: CardSet
+                , younger: CardSet
+                , led: Option<u32>
+                , elder_to_play: bool
+                , elder_tricks: u32
+                , younger_tricks: u32
+                // elder points while a pique is still possible
+                , pique: Option<u32>
+}
+
+impl Position {
+    // position after the first card, when younger's declarations have been scored
+    fn from_game(game: &Game) -> Self {
+        let (elder, younger) = (game.elder(), game.younger());
+        let led = game.get_card_played(elder).as_ref()
+            .or_else(|| game.get_card_played(younger).as_ref())
+            .map(|card| card.index());
+        let has_bonus = game.get_deal_moves().iter().any(|(gmove, _)| {
+            gmove.player() == elder && matches!(gmove.player_move(), PlayerMove::Pique | PlayerMove::Repique)
+        });
+        let pique = if game.get_deal_points(younger) == 0 && !has_bonus {
+            Some(game.get_deal_points(elder))
+        } else {
+            None
+        };
+        Position { elder: CardSet::from(game.get_hand(elder))
+                 , younger: CardSet::from(game.get_hand(younger))
+                 , led
+                 , elder_to_play: game.player_to_play() == Some(elder)
+                 , elder_tricks: game.get_tricks_won(elder)
+                 , younger_tricks: game.get_tricks_won(younger)
+                 , pique
+        }
+    }
+
+    fn is_over(&self) -> bool {
+        self.elder.is_empty() && self.younger.is_empty()
+    }
+
+    // Cards allowed by the follow suit rule, keeping one of the cards following each other
+    // among the cards still in play, as they win the same tricks
+    fn moves(&self) -> Vec<u32> {
+        let hand = if self.elder_to_play { self.elder } else { self.younger };
+        let in_play = self.elder.bits() | self.younger.bits() | self.led.map_or(0, |led| 1 << led);
+        let mut allowed = hand.bits();
+        if let Some(led) = self.led {
+            let suit = 0xff << (led & !7);
+            if allowed & suit != 0 {
+                allowed &= suit;
+            }
+        }
+
+        let mut moves = vec![];
+        let mut previous: Option<u32> = None;
+        while allowed != 0 {
+            let index = 31 - allowed.leading_zeros();
+            allowed &= !(1 << index);
+            let equivalent = previous.is_some_and(|previous| {
+                let between = ((1_u64 << previous) - 1) & !((2_u64 << index) - 1);
+                previous / 8 == index / 8 && in_play as u64 & between == 0
+            });
+            if !equivalent {
+                moves.push(index);
+            }
+            previous = Some(index);
+        }
+        moves
+    }
+
+    // Plays the card, returns the new position and the points scored by elder minus the points
+    // scored by younger, following the engine's rules
+    fn play(&self, index: u32) -> (Position, i32) {
+        let mut next = *self;
+        let (mut elder_points, mut younger_points): (i32, i32) = (0, 0);
+        if self.elder_to_play {
+            next.elder = CardSet::from_bits(self.elder.bits() & !(1 << index));
+        } else {
+            next.younger = CardSet::from_bits(self.younger.bits() & !(1 << index));
+        }
+
+        match self.led {
+            None => {
+                if self.elder_to_play { elder_points += 1 } else { younger_points += 1 }
+                next.led = Some(index);
+                next.elder_to_play = !self.elder_to_play;
+            },
+            Some(led) => {
+                let follower_wins = index / 8 == led / 8 && index > led;
+                let elder_wins = self.elder_to_play == follower_wins;
+                let mut points = if follower_wins { 1 } else { 0 };
+                if next.is_over() {
+                    points += 1;
+                }
+                if elder_wins {
+                    elder_points += points;
+                    next.elder_tricks += 1;
+                } else {
+                    younger_points += points;
+                    next.younger_tricks += 1;
+                }
+                next.led = None;
+                next.elder_to_play = elder_wins;
+            }
+        }
+
+        next.pique = match self.pique {
+            Some(_) if younger_points > 0 => None,
+            Some(points) if points as i32 + elder_points >= 30 => {
+                elder_points += PlayerMove::Pique.movePoints() as i32;
+                None
+            },
+            Some(points) => Some(points + elder_points as u32),
+            None => None
+        };
+        (next, elder_points - younger_points)
+    }
+
+    // majority of the tricks, or capot
+    fn cards_points(&self) -> i32 {
+        let points = |loser_tricks| if loser_tricks == 0 { PlayerMove::Capot } else { PlayerMove::WinCards }.movePoints() as i32;
+        if self.elder_tricks > self.younger_tricks {
+            points(self.younger_tricks)
+        } else if self.elder_tricks < self.younger_tricks {
+            -points(self.elder_tricks)
+        } else {
+            0
+        }
+    }
+}
+
+#[derive (Debug, Clone, Copy, PartialEq)]
+enum Bound { Exact, Lower, Upper }
+
+#[derive (Default)]
+pub struct Solver { table: HashMap<Position, (i32, Bound)> }
+
+impl Solver {
+    pub fn new() -> Self {
+        Solver::default()
+    }
+
+    // Points elder scores minus points younger scores from the position until the end of the deal
+    fn search(&mut self, position: &Position, mut alpha: i32, mut beta: i32) -> i32 {
+        if position.is_over() {
+            return position.cards_points();
+        }
+        if let Some(&(value, bound)) = self.table.get(position) {
+            match bound {
+                Bound::Exact => return value,
+                Bound::Lower => alpha = alpha.max(value),
+                Bound::Upper => beta = beta.min(value),
+            }
+            if alpha >= beta {
+                return value;
+            }
+        }
+
+        let (alpha_start, beta_start) = (alpha, beta);
+        let mut best = if position.elder_to_play { -INFINITY } else { INFINITY };
+        for index in position.moves() {
+            let (next, points) = position.play(index);
+            let value = points + self.search(&next, alpha - points, beta - points);
+            if position.elder_to_play {
+                best = best.max(value);
+                alpha = alpha.max(best);
+            } else {
+                best = best.min(value);
+                beta = beta.min(best);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best <= alpha_start {
+            Bound::Upper
+        } else if best >= beta_start {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        if self.table.len() >= TABLE_SIZE {
+            self.table.clear();
+        }
+        self.table.insert(*position, (best, bound));
+        best
+    }
+
+    // Each card the player to play can play, with the points the player scores minus the points
+    // the opponent scores from this card until the end of the deal, best first
+    pub fn card_values(&mut self, game: &Game) -> Result<Vec<(Card, i32)>, PiquetError> {
+        match game.get_step() {
+            Step::PlayFirstCard | Step::PlayCards => (),
+            step => return Err(PiquetError::InvalidForStepError(step.clone()))
+        }
+        let player = game.player_to_play().expect("No player to play");
+        let opponent = player.other();
+        let mut values: Vec<(Card, i32)> = game.legal_moves(player).into_iter().map(|pmove| {
+            let card = match &pmove {
+                PlayerMove::PlayFirst(card) | PlayerMove::PlayCard(card) => card.clone(),
+                _ => panic!("Not a card")
+            };
+            // the engine plays the card and the automatic steps following the first one
+            let mut next = game.clone();
+            next.play(player, pmove).expect("Illegal card");
+            let mut value = next.get_deal_points(player) as i32 - game.get_deal_points(player) as i32
+                - (next.get_deal_points(opponent) as i32 - game.get_deal_points(opponent) as i32);
+            if next.get_step() == &Step::PlayCards {
+                let elder_value = self.search(&Position::from_game(&next), -INFINITY, INFINITY);
+                value += if player == game.elder() { elder_value } else { -elder_value };
+            }
+            (card, value)
+        }).collect();
+        values.sort_by_key(|(_, value)| -value);
+        Ok(values)
+    }
+
+    pub fn best_card(&mut self, game: &Game) -> Result<Card, PiquetError> {
+        let values = self.card_values(game)?;
+        Ok(values[0].0.clone())
+    }
+
+    // Plays the best cards until the end of the deal. The positions of the previous deals are
+    // forgotten.
+    pub fn solve(&mut self, game: &Game) -> Result<Solution, PiquetError> {
+        self.table.clear();
+        let mut game = game.clone();
+        let mut line = vec![];
+        while let Step::PlayFirstCard | Step::PlayCards = game.get_step() {
+            let player = game.player_to_play().expect("No player to play");
+            let card = self.best_card(&game)?;
+            let pmove = if game.get_step() == &Step::PlayFirstCard || game.get_card_played(player.other()).is_none() {
+                PlayerMove::PlayFirst(card)
+            } else {
+                PlayerMove::PlayCard(card)
+            };
+            line.push(Move::new(player, pmove.clone()));
+            game.play(player, pmove)?;
+        }
+        Ok(Solution { player1_tricks: game.get_tricks_won(PlayerId::P1)
+                    , player2_tricks: game.get_tricks_won(PlayerId::P2)
+                    , player1_points: game.get_deal_points(PlayerId::P1)
+                    , player2_points: game.get_deal_points(PlayerId::P2)
+                    , line
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::*;
+    use crate::game::fixtures::game_at_first_card;
+    use rand::Rng;
+    use rand_core::SeedableRng;
+
+    fn position(elder: &str, younger: &str) -> Position {
+        Position { elder: CardSet::from(&elder.parse::<Hand>().unwrap())
+                 , younger: CardSet::from(&younger.parse::<Hand>().unwrap())
+                 , led: None
+                 , elder_to_play: true
+                 , elder_tricks: 5
+                 , younger_tricks: 5
+                 , pique: None
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let mut solver = Solver::new();
+        // leading the spade first keeps the ace to win the last trick as second
+        let ending = position("Ah 7s", "Kh 8s");
+        assert_eq!(solver.search(&ending, -INFINITY, INFINITY), (1 + 1 + 1) - (1 + 1));
+        // two leads, the last trick and the majority
+        let ending = position("Ah 9s", "Kh 8s");
+        assert_eq!(solver.search(&ending, -INFINITY, INFINITY), 1 + 1 + 1 + 10);
+        assert_eq!(ending.moves(), vec![Card::new(Rank::Nine, Suit::Spade).index(), Card::new(Rank::Ace, Suit::Heart).index()]);
+        // equivalent cards
+        assert_eq!(position("Ah Kh Jh", "Qh 7s 8s").moves().len(), 2);
+    }
+
+    #[test]
+    fn test_engine_rules() {
+        // random cards scored as the engine does
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([1; 16]);
+        for seed in 0..5 {
+            let mut game = game_at_first_card(seed);
+            let player = game.player_to_play().unwrap();
+            let pmove = RandomAgent::new([seed; 16]).choose_move(&game.view_for(player), &game.legal_moves(player));
+            game.play(player, pmove).unwrap();
+            let (elder, younger) = (game.elder(), game.younger());
+            let start = game.get_deal_points(elder) as i32 - game.get_deal_points(younger) as i32;
+            let mut position = Position::from_game(&game);
+            let mut points = 0;
+            while let Some(player) = game.player_to_play() {
+                let legal = game.legal_moves(player);
+                let pmove = legal[rng.gen_range(0, legal.len())].clone();
+                if let PlayerMove::PlayFirst(card) | PlayerMove::PlayCard(card) = &pmove {
+                    let (next, card_points) = position.play(card.index());
+                    position = next;
+                    points += card_points;
+                }
+                game.play(player, pmove).unwrap();
+            }
+            points += position.cards_points();
+            assert_eq!(game.get_deal_points(elder) as i32 - game.get_deal_points(younger) as i32, start + points);
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        let game = game_at_first_card(2);
+        let elder = game.elder();
+        let mut solver = Solver::new();
+        let values = solver.card_values(&game).unwrap();
+        assert_eq!(values.len(), 12);
+        let solution = solver.solve(&game).unwrap();
+        assert_eq!(solution.player1_tricks + solution.player2_tricks, 12);
+        assert_eq!(solution.line.len(), 24);
+        assert_eq!(solution.line[0], Move::new(elder, PlayerMove::PlayFirst(values[0].0.clone())));
+
+        // the best line scores what the best card is worth
+        let (elder_points, younger_points) = match elder {
+            PlayerId::P1 => (solution.player1_points, solution.player2_points),
+            PlayerId::P2 => (solution.player2_points, solution.player1_points),
+        };
+        let before = game.get_deal_points(elder) as i32 - game.get_deal_points(game.younger()) as i32;
+        assert_eq!(elder_points as i32 - younger_points as i32 - before, values[0].1);
+
+        // the table only keeps the positions of the last deal solved
+        let other = game_at_first_card(3);
+        solver.solve(&other).unwrap();
+        let mut fresh = Solver::new();
+        fresh.solve(&other).unwrap();
+        assert_eq!(solver.table.len(), fresh.table.len());
+        assert!(solver.table.len() <= TABLE_SIZE);
+    }
+}
@@ -0,0 +1,482 @@
+// Estimates how good a hand is for the declarations, by dealing the unseen cards at random to
+// the opponent
+use itertools::Itertools;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Serialize, Deserialize};
+
+use crate::cards::*;
+use crate::combinations::*;
+use crate::exchange::tricks_value;
+use crate::game::{declaration_response, matching_combination, DeclarationResponse, PlayerMove, HAND_SIZE};
+use crate::view::PlayerView;
+
+// opponent hands drawn before giving up matching the announcements
+const DRAW_TRIES: usize = 100;
+
+#[derive (Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandStrength { pub declaration_points: f64
+                        , pub point_win: f64
+                        , pub sequence_win: f64
+                        , pub set_win: f64
+                        , pub pique: f64
+                        , pub repique: f64
+}
+
+// Points scored by the hand if it wins the declaration
+fn winning_points(ctype: &CombinationType, hand: &Hand) -> u32 {
+    match ctype {
+        CombinationType::Point => get_best_combination(ctype, hand).map_or(0, |best| best.points() as u32),
+        _ => get_combinations(ctype, hand).iter().map(|c| c.points() as u32).sum()
+    }
+}
+
+// Cards shown to the opponent by the winner of the declaration
+fn shown_cards(ctype: &CombinationType, hand: &Hand) -> CardSet {
+    match ctype {
+        CombinationType::Point => get_best_combination(ctype, hand).map_or(CardSet::empty(), |best| CardSet::from(best.get_cards())),
+        _ => get_combinations(ctype, hand).iter().fold(CardSet::empty(), |cards, c| cards.union(CardSet::from(c.get_cards())))
+    }
+}
+
+// What the cards shown are worth to the opponent, who can then play around them : their
+// trick value, at about two points by trick as in exchange::hand_value
+fn shown_value(ctype: &CombinationType, hand: &Hand) -> f64 {
+    2.0 * tricks_value(shown_cards(ctype, hand))
+}
+
+// Points scored with the declarations of a combination type, given the opponent's hand
+pub fn declaration_points(ctype: &CombinationType, hand: &Hand, opponent: &Hand) -> Option<u32> {
+    let best = get_best_combination(ctype, hand);
+    let response = declaration_response(&best, &get_best_combination(ctype, opponent), true);
+    match (best, response) {
+        (Some(_), DeclarationResponse::Good) => Some(winning_points(ctype, hand)),
+        _ => None
+    }
+}
+
+// Tricks won in a row by leading the cards the opponent can't beat
+fn sure_tricks(hand: &Hand, opponent: &Hand) -> u32 {
+    Suit::iter().map(|suit| {
+        let opponent_max = opponent.iter().filter(|c| &c.suit == suit).map(|c| &c.rank).max();
+        hand.iter().filter(|c| &c.suit == suit && opponent_max.is_none_or(|max| &c.rank > max)).count() as u32
+    }).sum()
+}
+
+pub fn evaluate_hand<R: Rng>(hand: &Hand, is_elder: bool, samples: usize, rng: &mut R) -> HandStrength {
+    let known = CardSet::from(hand);
+    let mut unseen: Vec<Card> = CardSet::full().difference(known).iter().collect();
+    let ctypes = [CombinationType::Point, CombinationType::Sequence, CombinationType::Set];
+
+    let mut points = 0;
+    let mut wins = [0; 3];
+    let mut piques = 0;
+    let mut repiques = 0;
+    for _ in 0..samples {
+        unseen.shuffle(rng);
+        let opponent = Hand::new(unseen.iter().take(HAND_SIZE).cloned().collect());
+        let mut total = 0;
+        let mut opponent_scores = false;
+        for (idx, ctype) in ctypes.iter().enumerate() {
+            if let Some(ctype_points) = declaration_points(ctype, hand, &opponent) {
+                wins[idx] += 1;
+                total += ctype_points;
+            }
+            opponent_scores |= declaration_points(ctype, &opponent, hand).is_some();
+        }
+        points += total;
+        if !opponent_scores && total >= 30 {
+            repiques += 1;
+        } else if is_elder && !opponent_scores && total + sure_tricks(hand, &opponent) + 1 >= 30 {
+            // one point for each card led until the opponent wins a trick
+            piques += 1;
+        }
+    }
+
+    let samples = samples.max(1) as f64;
+    HandStrength { declaration_points: points as f64 / samples
+                 , point_win: wins[0] as f64 / samples
+                 , sequence_win: wins[1] as f64 / samples
+                 , set_win: wins[2] as f64 / samples
+                 , pique: piques as f64 / samples
+                 , repique: repiques as f64 / samples
+    }
+}
+
+#[derive (Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeclarationAdvice { pub combination_type: CombinationType
+                             , pub win_probability: f64
+                             // expected points won minus points lost on this declaration, less the
+                             // value of the cards shown to the opponent plus the value of the
+                             // cards the opponent shows
+                             , pub declare: f64
+                             , pub sink: f64
+                             // the points of the declaration don't make up for the cards shown
+                             , pub should_sink: bool
+}
+
+// What one of the opponent's moves of the deal tells about the opponent's hand before the
+// cards played : a declaration of elder (with the number of cards of the Equals when the value 
+// is declared), a response of younger to the player's declared combination, or a combination
+// shown when scored
+pub(crate) enum Announcement { Declared(CombinationType, PlayerMove, Option<usize>)
+                             , Response(CombinationType, DeclarationResponse, Option<Combination>, bool)
+                             , Shown(Combination)
+}
+
+impl Announcement {
+    pub(crate) fn is_matched_by(&self, hand: &Hand) -> bool {
+        match self {
+            Announcement::Declared(ctype, pmove, equals_count) => matching_combination(ctype, hand, pmove, *equals_count).is_some(),
+            Announcement::Response(ctype, response, mine, compare_value) 
+                => response == &declaration_response(mine, &get_best_combination(ctype, hand), *compare_value),
+            Announcement::Shown(comb) => comb.get_cards().iter().all(|card| hand.contains(card)),
+        }
+    }
+
+    // Sets of cards among `available` which make a hand match the announcement, None when any
+    // hand may match it
+    fn witnesses(&self, available: CardSet) -> Option<Vec<CardSet>> {
+        let ctype = match self {
+            Announcement::Shown(comb) => return Some(Some(CardSet::from(comb.get_cards()))
+                .filter(|cards| cards.is_subset(available))
+                .into_iter()
+                .collect()),
+            Announcement::Response(_, DeclarationResponse::Good, _, _) => return None,
+            Announcement::Declared(ctype, _, _) | Announcement::Response(ctype, _, _, _) => ctype,
+        };
+        Some((1..=8)
+            .flat_map(|len| combinations_of_len(ctype, available, len))
+            .filter(|cards| self.is_matched_by(&Hand::from(*cards)))
+            .collect())
+    }
+}
+
+// Sets of `len` cards among `available` forming a combination of the type
+fn combinations_of_len(ctype: &CombinationType, available: CardSet, len: usize) -> Vec<CardSet> {
+    let groups: Vec<CardSet> = match ctype {
+        CombinationType::Point => Suit::iter().map(|suit| available.suit(suit)).collect(),
+        CombinationType::Set => Rank::iter()
+            .filter(|&rank| rank > &Rank::Nine)
+            .map(|rank| available.intersection(CardSet::rank_mask(rank)))
+            .collect(),
+        CombinationType::Sequence => return get_card_set_combinations(ctype, available).into_iter()
+            .flat_map(|run| {
+                let cards: Vec<Card> = run.iter().collect();
+                cards.windows(len).map(|window| CardSet::from(&Hand::new(window.to_vec()))).collect::<Vec<_>>()
+            })
+            .collect(),
+    };
+    groups.into_iter()
+        .flat_map(|group| group.iter().combinations(len).map(|cards| CardSet::from(&Hand::new(cards))))
+        .collect()
+}
+
+// The opponent's announcements of the deal. Elder may declare less than the best combination
+// or nothing at all, so a declaration only tells that the hand holds a matching combination
+// and declaring 0 tells nothing.
+pub(crate) fn announcements(view: &PlayerView) -> Vec<Announcement> {
+    let hand = view.before_play(view.player, &view.hand);
+    // number of cards declared by elder and the player's declared combinations, by type
+    let mut counts: Vec<(CombinationType, usize)> = vec![];
+    let mut mine: Vec<(CombinationType, Option<Combination>)> = vec![];
+    let mut value_stages: Vec<CombinationType> = vec![];
+    let mut result = vec![];
+    for gmove in view.dealMoves.iter() {
+        let pmove = gmove.player_move();
+        let is_mine = gmove.player() == view.player;
+        match pmove {
+            PlayerMove::DeclarationCount(ctype, _) | PlayerMove::DeclarationUpper(ctype, _) => {
+                let equals_count = if value_stages.contains(ctype) {
+                    counts.iter().rev().find(|(c, _)| c == ctype).map(|(_, count)| *count)
+                } else {
+                    None
+                };
+                if let (None, PlayerMove::DeclarationCount(_, count)) = (equals_count, pmove) {
+                    counts.push((ctype.clone(), *count as usize));
+                }
+                if is_mine {
+                    mine.push((ctype.clone(), matching_combination(ctype, &hand, pmove, equals_count)));
+                } else if equals_count.is_some() || pmove != &PlayerMove::DeclarationCount(ctype.clone(), 0) {
+                    result.push(Announcement::Declared(ctype.clone(), pmove.clone(), equals_count));
+                }
+            },
+            PlayerMove::PlayerResponse(ctype, response) => {
+                if !is_mine {
+                    let declared = mine.iter().rev().find(|(c, _)| c == ctype).and_then(|(_, comb)| comb.clone());
+                    result.push(Announcement::Response(ctype.clone(), response.clone(), declared, value_stages.contains(ctype)));
+                }
+                if response == &DeclarationResponse::Equals {
+                    value_stages.push(ctype.clone());
+                }
+            },
+            PlayerMove::Declaration(comb) if !is_mine => result.push(Announcement::Shown(comb.clone())),
+            _ => ()
+        }
+    }
+    result
+}
+
+// Draws the opponent's current hand : the `fixed` cards, then cards among `unseen` up to `size`
+// cards, matching the opponent's announcements. When a random draw doesn't match them, the
+// combinations announced are dealt first. None when no matching hand was found.
+pub(crate) fn draw_opponent_hand<R: Rng>(view: &PlayerView, announcements: &[Announcement], fixed: CardSet, unseen: &[Card], size: usize, rng: &mut R) -> Option<Hand> {
+    let played = CardSet::from(&view.before_play(view.player.other(), &Hand::empty_hand()));
+    let start = fixed.union(played);
+    let total = size + played.len();
+    let mut pool = unseen.to_vec();
+    let fill = |mut hand: CardSet, pool: &[Card]| {
+        for card in pool.iter() {
+            if hand.len() >= total {
+                break;
+            }
+            hand.insert(card);
+        }
+        hand
+    };
+    let matches = |hand: CardSet| hand.len() == total 
+        && announcements.iter().all(|announcement| announcement.is_matched_by(&Hand::from(hand)));
+
+    for _ in 0..DRAW_TRIES {
+        pool.shuffle(rng);
+        let drawn = fill(start, &pool);
+        if matches(drawn) {
+            return Some(Hand::from(drawn.difference(played)));
+        }
+
+        let mut hand = start;
+        let available = start.union(CardSet::from(&Hand::new(pool.clone())));
+        for announcement in announcements.iter() {
+            if announcement.is_matched_by(&Hand::from(hand)) {
+                continue;
+            }
+            if let Some(witnesses) = announcement.witnesses(available) {
+                let witnesses: Vec<CardSet> = witnesses.into_iter().filter(|cards| hand.union(*cards).len() <= total).collect();
+                if let Some(cards) = witnesses.choose(rng) {
+                    hand = hand.union(*cards);
+                }
+            }
+        }
+        let drawn = fill(hand, &pool);
+        if matches(drawn) {
+            return Some(Hand::from(drawn.difference(played)));
+        }
+    }
+    None
+}
+
+// Cards of the opponent's carte blanche discarded at the exchange, drawn at random among the
+// `shown` cards the opponent has not declared since
+pub(crate) fn draw_shown_discard<R: Rng>(view: &PlayerView, shown: CardSet, rng: &mut R) -> CardSet {
+    let declared = view.dealMoves.iter()
+        .filter(|gmove| gmove.player() != view.player)
+        .fold(CardSet::empty(), |cards, gmove| match gmove.player_move() {
+            PlayerMove::Declaration(comb) => cards.union(CardSet::from(comb.get_cards())),
+            _ => cards
+        });
+    let candidates: Vec<Card> = shown.difference(declared).iter().collect();
+    let count = view.opponentDiscardCount.unwrap_or(0);
+    candidates.choose_multiple(rng, count).fold(CardSet::empty(), |mut cards, card| { cards.insert(card); cards })
+}
+
+// Compares declaring each combination type with sinking it, over opponent hands consistent 
+// with what the opponent has announced during the deal and the cards known to be theirs
+pub fn advise_declarations<R: Rng>(view: &PlayerView, samples: usize, rng: &mut R) -> Vec<DeclarationAdvice> {
+    let known = view.known_cards();
+    let unseen: Vec<Card> = CardSet::full().difference(known.all()).iter().collect();
+    let size = view.opponentCardsCount.min(unseen.len() + known.opponent().len());
+
+    let announcements = announcements(view);
+    let opponents: Vec<Hand> = (0..samples)
+        .filter_map(|_| {
+            let fixed = known.opponent().difference(draw_shown_discard(view, known.shown, rng));
+            draw_opponent_hand(view, &announcements, fixed, &unseen, size, rng)
+        })
+        .collect();
+
+    [CombinationType::Point, CombinationType::Sequence, CombinationType::Set].iter().map(|ctype| {
+        let mut wins = 0;
+        let mut declare = 0.0;
+        let mut sink = 0.0;
+        for opponent in opponents.iter() {
+            // sinking concedes the declaration to the opponent, who shows the combination
+            let lost = winning_points(ctype, opponent) as f64 - shown_value(ctype, opponent);
+            match declaration_points(ctype, &view.hand, opponent) {
+                Some(points) => { wins += 1; declare += points as f64 - shown_value(ctype, &view.hand) },
+                None if declaration_points(ctype, opponent, &view.hand).is_some() => declare -= lost,
+                None => ()
+            }
+            if get_best_combination(ctype, opponent).is_some() {
+                sink -= lost;
+            }
+        }
+        let count = opponents.len().max(1) as f64;
+        let (declare, sink) = (declare / count, sink / count);
+        DeclarationAdvice { combination_type: ctype.clone()
+                          , win_probability: wins as f64 / count
+                          , declare
+                          , sink
+                          , should_sink: declare <= sink
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::SeedableRng;
+    use crate::game::fixtures::dealt_game;
+
+    fn evaluate(hand: &str, is_elder: bool) -> HandStrength {
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([1; 16]);
+        evaluate_hand(&hand.parse().unwrap(), is_elder, 500, &mut rng)
+    }
+
+    #[test]
+    fn test_declaration_points() {
+        let hand: Hand = "7h 8h 9h 10h Kd Ks Kc Kh Qs Js 10s 7c".parse().unwrap();
+        let opponent: Hand = "Ah Jh Qh 8d 9d 10d Jd Ad 7s 8s 9s 8c".parse().unwrap();
+        assert_eq!(declaration_points(&CombinationType::Point, &hand, &opponent), None);
+        assert_eq!(declaration_points(&CombinationType::Point, &opponent, &hand), Some(5));
+        // quart to king, quart to ten
+        assert_eq!(declaration_points(&CombinationType::Sequence, &hand, &opponent), Some(8));
+        assert_eq!(declaration_points(&CombinationType::Set, &hand, &opponent), Some(14));
+    }
+
+    #[test]
+    fn test_advise_declarations() {
+        use crate::game::*;
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([5; 16]);
+        // younger has the longest point, then the shortest one
+        for &(seed, younger_wins) in [(0, true), (1, false)].iter() {
+            let mut game = dealt_game(seed);
+            let elder = game.elder();
+            let younger = game.younger();
+            for &player in [elder, younger].iter() {
+                let card = game.get_hand(player).iter().next().unwrap().clone();
+                game.play(player, PlayerMove::Exchange(Hand::new(vec![card]))).unwrap();
+            }
+            let elder_point = get_best_combination(&CombinationType::Point, game.get_hand(elder)).unwrap();
+            game.play(elder, PlayerMove::DeclarationCount(CombinationType::Point, elder_point.len() as u32)).unwrap();
+
+            // younger knows the length of elder's point
+            let advice = advise_declarations(&game.view_for(younger), 100, &mut rng);
+            assert_eq!(advice.len(), 3);
+            let point = &advice[0];
+            assert_eq!(point.win_probability, if younger_wins { 1.0 } else { 0.0 });
+            // losing anyway, better not to show anything
+            if !younger_wins {
+                assert!(point.should_sink);
+            }
+            assert!(advice.iter().all(|a| a.should_sink == (a.declare <= a.sink)));
+        }
+    }
+
+    #[test]
+    fn test_advise_sink() {
+        let game = dealt_game(0);
+        // the talon is shown, the opponent's hand is known : no set, no sequence
+        let hand: Hand = "Kh Kd Ks 7h 8h 7d 9d 8s 10s 7c 9c Jc".parse().unwrap();
+        let opponent: Hand = "Ah Ad Qs Qc Jh Jd 10h 10c Kc 9h 9s 8c".parse().unwrap();
+        let talon = CardSet::full().difference(CardSet::from(&hand)).difference(CardSet::from(&opponent));
+        let mut view = game.view_for(game.younger());
+        view.hand = hand;
+        view.talonSeen = Hand::empty_hand();
+        view.visible = Deck::empty_deck();
+        view.visible.add_cards(talon.iter().collect());
+        view.dealMoves = vec![];
+
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([5; 16]);
+        let advice = advise_declarations(&view, 10, &mut rng);
+        // the trio of kings scores 3 points but shows the kings
+        let set = &advice[2];
+        assert_eq!(set.win_probability, 1.0);
+        assert_eq!(set.sink, 0.0);
+        assert!(set.declare < set.sink);
+        assert!(set.should_sink);
+        // the opponent's point of four hearts wins either way
+        let point = &advice[0];
+        assert_eq!(point.win_probability, 0.0);
+        assert_eq!(point.declare, point.sink);
+    }
+
+    fn is_consistent(view: &PlayerView, opponent: &Hand) -> bool {
+        let hand = view.before_play(view.player.other(), opponent);
+        announcements(view).iter().all(|announcement| announcement.is_matched_by(&hand))
+    }
+
+    #[test]
+    fn test_announcements() {
+        use crate::game::*;
+        use CombinationType::Point;
+        let game = dealt_game(0);
+        let (elder, younger) = (game.elder(), game.younger());
+        let hand = |cards: &str| cards.parse::<Hand>().unwrap();
+
+        // elder may declare a shorter point than the best one, or nothing
+        let mut view = game.view_for(younger);
+        view.dealMoves = vec![Move::new(elder, PlayerMove::DeclarationCount(Point, 4))];
+        assert!(is_consistent(&view, &hand("7h 8h 9h 10h Jh 7s 8s 9s 10s Ac Kc Qd")));
+        assert!(!is_consistent(&view, &hand("7h 8h 9h 10h Jh 7s 8s 9s Ac Kc Qd Jd")));
+        view.dealMoves = vec![Move::new(elder, PlayerMove::DeclarationCount(Point, 0))];
+        assert!(is_consistent(&view, &hand("7h 8h 9h 10h Jh 7s 8s 9s Ac Kc Qd Jd")));
+
+        // younger's point beats elder's five hearts : six clubs or six diamonds
+        let mut view = game.view_for(elder);
+        view.hand = hand("7h 8h 9h 10h Jh 7s 8s 9s Ac Kc Qd Jd");
+        view.talonSeen = Hand::empty_hand();
+        view.visible = Deck::empty_deck();
+        view.dealMoves = vec![ Move::new(elder, PlayerMove::DeclarationCount(Point, 5))
+                             , Move::new(younger, PlayerMove::PlayerResponse(Point, DeclarationResponse::NotGood))
+        ];
+        let unseen: Vec<Card> = CardSet::full().difference(view.known_cards().all()).iter().collect();
+        let announcements = announcements(&view);
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([6; 16]);
+        for _ in 0..10 {
+            let opponent = draw_opponent_hand(&view, &announcements, CardSet::empty(), &unseen, 12, &mut rng).unwrap();
+            assert_eq!(opponent.len(), 12);
+            assert!(is_consistent(&view, &opponent));
+            assert_eq!(get_best_combination(&Point, &opponent).unwrap().len(), 6);
+        }
+
+        // younger's carte blanche : the discards are among the cards shown
+        let mut view = game.view_for(elder);
+        let shown = game.get_hand(younger).clone();
+        view.visible = Deck::empty_deck();
+        view.visible.add_cards(shown.clone().cards());
+        view.dealMoves = vec![Move::new(younger, PlayerMove::CarteBlanche)];
+        view.opponentDiscardCount = Some(2);
+        let known = view.known_cards();
+        let discard = draw_shown_discard(&view, known.shown, &mut rng);
+        assert_eq!(discard.len(), 2);
+        assert!(discard.is_subset(CardSet::from(&shown)));
+    }
+
+    #[test]
+    fn test_sure_tricks() {
+        let hand: Hand = "Ah Kh 9h As 7d Ac Kc".parse().unwrap();
+        let opponent: Hand = "Qh 10h Ks Qs Ad Qc".parse().unwrap();
+        // hearts ace and king, spade ace, all clubs
+        assert_eq!(sure_tricks(&hand, &opponent), 5);
+    }
+
+    #[test]
+    fn test_evaluate_hand() {
+        let strong = evaluate("7h 8h 9h 10h Jh Qh Kh Ah Kd Ks Kc 9d", true);
+        assert_eq!(strong.point_win, 1.0);
+        assert_eq!(strong.sequence_win, 1.0);
+        assert!(strong.repique > 0.9);
+        assert!(strong.declaration_points > 30.0);
+
+        let weak = evaluate("7h 9h Jd 7d 8s 10s Qs 7c 9c Jc 8d 10h", false);
+        assert!(weak.point_win < 0.2);
+        assert_eq!(weak.set_win, 0.0);
+        assert_eq!(weak.pique + weak.repique, 0.0);
+        assert!(weak.declaration_points < strong.declaration_points);
+
+        // pique is only for elder
+        let good_younger = evaluate("Ah Kh Qh 10h As Ks Qs Ad Kd Qd Ac 7c", false);
+        assert_eq!(good_younger.pique, 0.0);
+        assert!(good_younger.repique > 0.3);
+    }
+}
@@ -0,0 +1,233 @@
+// field names follow the ones of the game module
+#![allow(non_snake_case)]
+
+use serde::{Serialize, Deserialize};
+
+use crate::cards::*;
+use crate::combinations::CombinationType;
+use crate::game::*;
+
+// Redacted state of the game for one player : the opponent's hand, the talon and the 
+// opponent's discards stay hidden
+#[derive (Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerView { pub player: PlayerId
+                      , pub isElder: bool
+                      , pub dealNum: Deal
+                      , pub step: Step
+                      , pub playerToPlay: Option<PlayerId>
+                      , pub hand: Hand
+                      , pub talonSeen: Hand
+                      , pub talonCount: usize
+                      , pub opponentCardsCount: usize
+                      , pub opponentDiscardCount: Option<usize>
+                      , pub visible: Deck
+                      , pub cardPlayed: Option<Card>
+                      , pub opponentCardPlayed: Option<Card>
+                      , pub dealPoints: u32
+                      , pub opponentDealPoints: u32
+                      , pub gamePoints: u32
+                      , pub opponentGamePoints: u32
+                      , pub tricksWon: u32
+                      , pub opponentTricksWon: u32
+                      , pub pointWinner: DeclarationWinner
+                      , pub sequenceWinner: DeclarationWinner
+                      , pub setWinner: DeclarationWinner
+                      // moves of the current deal, without the opponent's exchange
+                      , pub dealMoves: Vec<Move>
+}
+
+impl PlayerView {
+    pub fn new(game: &Game, player: PlayerId) -> Self {
+        let opponent = player.other();
+        let mut opponentDiscardCount = None;
+        let mut dealMoves = vec![];
+        for (gmove, _) in game.get_deal_moves() {
+            match gmove.player_move() {
+                PlayerMove::Exchange(discard) if gmove.player() == opponent => {
+                    opponentDiscardCount = Some(discard.len());
+                },
+                _ => dealMoves.push(gmove.clone())
+            }
+        }
+
+        PlayerView { player
+                   , isElder: game.elder() == player
+                   , dealNum: game.get_deal_num().clone()
+                   , step: game.get_step().clone()
+                   , playerToPlay: game.player_to_play()
+                   , hand: game.get_hand(player).clone()
+                   , talonSeen: game.get_talon_seen(player).clone()
+                   , talonCount: game.get_talon().len()
+                   , opponentCardsCount: game.get_hand(opponent).len()
+                   , opponentDiscardCount
+                   , visible: game.get_visible().clone()
+                   , cardPlayed: game.get_card_played(player).clone()
+                   , opponentCardPlayed: game.get_card_played(opponent).clone()
+                   , dealPoints: game.get_deal_points(player)
+                   , opponentDealPoints: game.get_deal_points(opponent)
+                   , gamePoints: game.get_game_points(player)
+                   , opponentGamePoints: game.get_game_points(opponent)
+                   , tricksWon: game.get_tricks_won(player)
+                   , opponentTricksWon: game.get_tricks_won(opponent)
+                   , pointWinner: game.get_declaration_winner(&CombinationType::Point).clone()
+                   , sequenceWinner: game.get_declaration_winner(&CombinationType::Sequence).clone()
+                   , setWinner: game.get_declaration_winner(&CombinationType::Set).clone()
+                   , dealMoves
+        }
+    }
+
+    // Hand of the player before the cards played during the deal, from the current one
+    pub fn before_play(&self, player: PlayerId, hand: &Hand) -> Hand {
+        let played = self.dealMoves.iter()
+            .filter(|gmove| gmove.player() == player)
+            .filter_map(|gmove| match gmove.player_move() {
+                PlayerMove::PlayFirst(card) | PlayerMove::PlayCard(card) => Some(card.clone()),
+                _ => None
+            });
+        Hand::new(hand.iter().cloned().chain(played).collect())
+    }
+
+    // Cards of the talon seen by the player, split into those taken by the opponent and those
+    // left : younger takes the first cards of the talon, among which the ones elder has seen
+    fn talon_seen_split(&self) -> (CardSet, Vec<Card>) {
+        let taken = if self.isElder { self.opponentDiscardCount.unwrap_or(0) } else { 0 };
+        let seen: Vec<Card> = self.talonSeen.iter().cloned().collect();
+        let (taken, left) = seen.split_at(taken.min(seen.len()));
+        (CardSet::from(&Hand::new(taken.to_vec())), left.to_vec())
+    }
+
+    // Cards of the talon seen by the player and still in it, in the talon order
+    pub fn talon_seen_left(&self) -> Vec<Card> {
+        let (_, left) = self.talon_seen_split();
+        left.into_iter().take(self.talonCount).collect()
+    }
+
+    // Where the player knows the cards they don't hold are
+    pub fn known_cards(&self) -> KnownCards {
+        let mut mine = CardSet::from(&self.hand);
+        let mut played = CardSet::empty();
+        let mut carte_blanche = vec![];
+        for gmove in self.dealMoves.iter() {
+            match gmove.player_move() {
+                PlayerMove::Exchange(discard) if gmove.player() == self.player => mine = mine.union(CardSet::from(discard)),
+                PlayerMove::PlayFirst(card) | PlayerMove::PlayCard(card) => played.insert(card),
+                PlayerMove::CarteBlanche => carte_blanche.push(gmove.player()),
+                _ => ()
+            }
+        }
+        // the hands of the cartes blanches are shown first, in the order of the declarations
+        let shown_cards = self.visible.get_cards();
+        let shown = match carte_blanche.iter().position(|&player| player != self.player) {
+            Some(idx) => shown_cards.iter().skip(idx * HAND_SIZE).take(HAND_SIZE).cloned().collect(),
+            None => vec![]
+        };
+        let shown = CardSet::from(&Hand::new(shown)).difference(mine).difference(played);
+        let (taken, _) = self.talon_seen_split();
+        let taken = taken.difference(played);
+        let out_of_play = mine.union(played)
+            .union(CardSet::from(&self.talonSeen).difference(taken))
+            .union(CardSet::from(&Hand::new(shown_cards.clone())).difference(shown));
+        KnownCards { out_of_play, taken, shown }
+    }
+}
+
+// Cards whose place the player knows, besides their own hand. The cards out of play are not the
+// opponent's : the player's cards and discards, the cards played, the cards of the talon seen
+// and left, the other cards shown.
+#[derive (Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownCards { pub out_of_play: CardSet
+                      // in the opponent's hand : the cards of the talon seen and taken
+                      , pub taken: CardSet
+                      // of the opponent's carte blanche and not played : in the hand or discarded
+                      , pub shown: CardSet
+}
+
+impl KnownCards {
+    pub fn opponent(&self) -> CardSet {
+        self.taken.union(self.shown)
+    }
+
+    // cards which are not in the talon at the exchange
+    pub fn all(&self) -> CardSet {
+        self.out_of_play.union(self.opponent())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::dealt_game;
+
+    #[test]
+    fn test_view_hides_opponent_cards() {
+        let mut game = dealt_game(3);
+        let elder = game.elder();
+        let younger = game.younger();
+        let discard = Hand::new(game.get_hand(elder).iter().take(3).cloned().collect());
+        game.play(elder, PlayerMove::Exchange(discard.clone())).unwrap();
+
+        let view = game.view_for(younger);
+        assert_eq!(&view.hand, game.get_hand(younger));
+        assert_eq!(view.opponentCardsCount, 12);
+        assert_eq!(view.opponentDiscardCount, Some(3));
+        assert_eq!(view.talonCount, 5);
+        assert!(view.dealMoves.is_empty());
+        assert_eq!(view.step, Step::ExchangeYounger);
+        assert_eq!(view.playerToPlay, Some(younger));
+
+        let json = serde_json::to_string(&view).unwrap();
+        let elder_cards = game.get_hand(elder).iter().chain(discard.iter());
+        for card in elder_cards {
+            assert!(!json.contains(&serde_json::to_string(card).unwrap()));
+        }
+        assert_eq!(serde_json::from_str::<PlayerView>(&json).unwrap(), view);
+
+        let view = game.view_for(elder);
+        assert_eq!(view.dealMoves, vec![Move::new(elder, PlayerMove::Exchange(discard))]);
+        assert_eq!(view.opponentDiscardCount, None);
+    }
+
+    #[test]
+    fn test_known_cards() {
+        let mut game = dealt_game(11);
+        let (elder, younger) = (game.elder(), game.younger());
+        let discard = |game: &Game, player, count| Hand::new(game.get_hand(player).iter().take(count).cloned().collect());
+        game.play(elder, PlayerMove::Exchange(discard(&game, elder, 3))).unwrap();
+        let seen = game.view_for(elder).talonSeen;
+        assert_eq!(seen.len(), 2);
+        game.play(younger, PlayerMove::Exchange(discard(&game, younger, 1))).unwrap();
+
+        // younger took the first card elder saw, the second one is left on top of the talon
+        let view = game.view_for(elder);
+        let known = view.known_cards();
+        let first = seen.iter().next().unwrap().clone();
+        let second = seen.iter().nth(1).unwrap().clone();
+        assert!(game.get_hand(younger).contains(&first));
+        assert_eq!(known.taken, CardSet::from(&Hand::new(vec![first.clone()])));
+        assert!(!known.out_of_play.contains(&first));
+        assert!(known.out_of_play.contains(&second));
+        assert_eq!(view.talon_seen_left(), vec![second.clone()]);
+        assert_eq!(game.get_talon().get_cards()[0], second);
+        // younger sees the rest of the talon, which elder can't take any more
+        let view = game.view_for(younger);
+        assert_eq!(view.known_cards().taken, CardSet::empty());
+        assert_eq!(view.talon_seen_left(), game.get_talon().get_cards().clone());
+
+        // elder's carte blanche, shown to younger
+        let mut view = game.view_for(younger);
+        let shown = game.get_hand(elder).clone();
+        view.visible = Deck::empty_deck();
+        view.visible.add_cards(shown.clone().cards());
+        view.dealMoves.insert(0, Move::new(elder, PlayerMove::CarteBlanche));
+        let known = view.known_cards();
+        assert_eq!(known.shown, CardSet::from(&shown));
+        assert_eq!(known.out_of_play.intersection(known.shown), CardSet::empty());
+        let played = shown.iter().next().unwrap().clone();
+        view.dealMoves.push(Move::new(elder, PlayerMove::PlayFirst(played.clone())));
+        let known = view.known_cards();
+        assert!(!known.shown.contains(&played) && known.out_of_play.contains(&played));
+        // nothing to learn from one's own carte blanche
+        view.dealMoves[0] = Move::new(younger, PlayerMove::CarteBlanche);
+        assert_eq!(view.known_cards().shown, CardSet::empty());
+    }
+}